@@ -0,0 +1,318 @@
+//! This module contains the configuration file, read from `config.toml` in the
+//! [config directory](util/fn.config_dir.html).
+//!
+//! Top-level keys specify defaults, which `[profiles.NAME]` tables override,
+//! and both are overriden by command-line arguments.
+//! Credentials are only allowed in profiles, and only sent to the origin of the profile's `url`.
+//!
+//! If it holds passwords or tokens, the file mustn't be accessible by other users.
+//!
+//! Unknown keys are an error.
+//!
+//! Key|Type|Meaning
+//! ---|----|-------
+//! `url` | string | remote directory the profile opens, only in profiles
+//! `user` | string | user to authenticate as with HTTP Basic authentication, only in profiles
+//! `password` | string | password for `user`, only in profiles
+//! `token` | string | token to authenticate with HTTP Bearer authentication, only in profiles
+//! `credentials` | string | `machine` to look up in the credentials file and `~/.netrc` instead of the remote host, only in profiles
+//! `ca_bundle` | path | additional certificate authority to trust
+//! `client_cert` | path | PKCS#12 client certificate and key to present to servers
+//! `client_cert_password` | string | password for `client_cert`
+//! `insecure` | boolean | skip verifying server hostnames, certificate chains are still verified
+//! `proxy` | string | proxy to route all requests through
+//! `timeout` | integer | time connecting and each read or write of a request may take, in seconds, one value for both
+//! `retries` | integer | maximal amount of times to retry transiently failed requests
+//! `retry_backoff` | integer | delay before the first retry, doubled for each next one, in seconds
+//! `max_redirects` | integer | maximal amount of redirects to follow
+//! `download_dir` | path | directory to download files into by default
+//! `sort` | string | listing order, `"name"`, `"size"` or `"modified"`
+//! `keys` | table | single-character key bindings, see [`KeyBindings`](ops/struct.KeyBindings.html)
+//!
+//! Paths starting with `~/` are relative to the home directory.
+//!
+//! # Examples
+//!
+//! ```toml
+//! sort = "modified"
+//! download_dir = "~/Downloads"
+//!
+//! [keys]
+//! download = "s"
+//!
+//! [profiles.work]
+//! url = "https://files.example.com/shared/"
+//! user = "me"
+//! ca_bundle = "~/.config/doh/work-ca.pem"
+//! ```
+//!
+//! With the above, `doh work` browses `https://files.example.com/shared/`.
+
+
+use self::super::ops::{KeyBindings, SortOrder};
+use self::super::util::{config_dir, home_dir};
+use self::super::ops::netrc::check_permissions;
+use std::collections::BTreeMap;
+use std::path::{PathBuf, Path};
+use toml::value::Table;
+use std::io::{self, Read};
+use std::fs::File;
+use reqwest::Url;
+use toml::Value;
+
+
+/// The parsed configuration file.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+pub struct Config {
+    /// Values used when no profile is selected, and when the selected profile doesn't specify them.
+    pub defaults: Profile,
+    /// Named profiles.
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// A set of configuration values, all optional.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+pub struct Profile {
+    /// Remote directory the profile opens.
+    pub url: Option<Url>,
+    /// User to authenticate as with HTTP Basic authentication.
+    pub user: Option<String>,
+    /// Password for `user`.
+    pub password: Option<String>,
+    /// Token to authenticate with HTTP Bearer authentication.
+    pub token: Option<String>,
+    /// `machine` to look up stored credentials for instead of the remote host.
+    pub credentials: Option<String>,
+    /// Additional certificate authority to trust.
+    pub ca_bundle: Option<PathBuf>,
+    /// PKCS#12 client certificate and key to present to servers.
+    pub client_cert: Option<PathBuf>,
+    /// Password for `client_cert`.
+    pub client_cert_password: Option<String>,
+    /// Whether to skip verifying server hostnames.
+    pub insecure: Option<bool>,
+    /// Proxy to route all requests through.
+    pub proxy: Option<String>,
+    /// Time connecting and each read of a request may take, in seconds.
+    pub timeout: Option<u64>,
+    /// Maximal amount of times to retry transiently failed requests.
+    pub retries: Option<usize>,
+    /// Delay before the first retry, in seconds.
+    pub retry_backoff: Option<u64>,
+    /// Maximal amount of redirects to follow.
+    pub max_redirects: Option<usize>,
+    /// Directory to download files into by default.
+    pub download_dir: Option<PathBuf>,
+    /// Listing order.
+    pub sort: Option<SortOrder>,
+    /// Key binding overrides, by action.
+    pub keys: BTreeMap<String, char>,
+}
+
+impl Config {
+    /// Get the path to the default configuration file, if the config directory is known.
+    pub fn default_path() -> Option<PathBuf> {
+        config_dir().map(|c| c.join("config.toml"))
+    }
+
+    /// Read and parse the configuration file at the specified path.
+    ///
+    /// A nonexistant file is equivalent to an empty one;
+    /// one holding passwords or tokens that's accessible by other users is an error.
+    pub fn load(p: &Path) -> Result<Config, String> {
+        let mut data = String::new();
+        let file = match File::open(p) {
+            Ok(mut f) => {
+                if let Err(e) = f.read_to_string(&mut data) {
+                    return Err(format!("Couldn't read {}: {}", p.display(), e));
+                }
+                Some(f)
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("Couldn't open {}: {}", p.display(), e)),
+        };
+
+        let config = try!(Config::parse(&data).map_err(|e| format!("{}: {}", p.display(), e)));
+        if let Some(f) = file {
+            if config.has_secrets() {
+                try!(check_permissions(&f).map_err(|e| format!("{} holds secrets, but is {}", p.display(), e)));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Parse configuration from TOML.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use doh::Config;
+    /// let config = Config::parse("timeout = 10\n[profiles.work]\nurl = \"https://example.com\"\ntimeout = 60").unwrap();
+    /// assert_eq!(config.defaults.timeout, Some(10));
+    /// assert_eq!(config.profile("work").timeout, Some(60));
+    /// assert_eq!(config.profile("work").url, Some("https://example.com".parse().unwrap()));
+    /// assert!(Config::parse("timeout = \"10\"").is_err());
+    /// assert!(Config::parse("token = \"secret\"").is_err());
+    /// assert!(Config::parse("retires = 3").is_err());
+    /// ```
+    pub fn parse(data: &str) -> Result<Config, String> {
+        let root = try!(data.parse::<Value>().map_err(|e| e.to_string()));
+        let root = try!(root.as_table().ok_or_else(|| "not a table".to_string()));
+        for key in &["user", "password", "token", "credentials"] {
+            if root.contains_key(*key) {
+                return Err(format!("{}: only allowed in profiles", key));
+            }
+        }
+
+        let mut profiles = BTreeMap::new();
+        if let Some(p) = root.get("profiles") {
+            for (name, profile) in try!(p.as_table().ok_or_else(|| "profiles: not a table".to_string())) {
+                let profile = try!(profile.as_table().ok_or_else(|| format!("profiles.{}: not a table", name)));
+                if profile.contains_key("profiles") {
+                    return Err(format!("profiles.{}.profiles: only allowed at the top level", name));
+                }
+                profiles.insert(name.clone(), try!(Profile::parse(profile).map_err(|e| format!("profiles.{}.{}", name, e))));
+            }
+        }
+
+        Ok(Config {
+            defaults: try!(Profile::parse(root)),
+            profiles: profiles,
+        })
+    }
+
+    /// Check whether any profile or the defaults hold a password or a token.
+    pub fn has_secrets(&self) -> bool {
+        Some(&self.defaults).into_iter().chain(self.profiles.values()).any(|p| {
+            p.password.is_some() || p.token.is_some() || p.client_cert_password.is_some()
+        })
+    }
+
+    /// Get the values for the specified profile, falling back to the defaults,
+    /// or just the defaults if there's no such profile.
+    pub fn profile(&self, name: &str) -> Profile {
+        match self.profiles.get(name) {
+            Some(p) => p.clone().or(self.defaults.clone()),
+            None => self.defaults.clone(),
+        }
+    }
+}
+
+impl Profile {
+    /// Keys a profile may specify.
+    const KEYS: &'static [&'static str] = &["url",
+                                            "user",
+                                            "password",
+                                            "token",
+                                            "credentials",
+                                            "ca_bundle",
+                                            "client_cert",
+                                            "client_cert_password",
+                                            "insecure",
+                                            "proxy",
+                                            "timeout",
+                                            "retries",
+                                            "retry_backoff",
+                                            "max_redirects",
+                                            "download_dir",
+                                            "sort",
+                                            "keys"];
+
+    /// Parse a profile from a TOML table, ignoring the `profiles` key.
+    ///
+    /// Errors start with the offending key.
+    fn parse(t: &Table) -> Result<Profile, String> {
+        if let Some(key) = t.keys().find(|k| *k != "profiles" && !Profile::KEYS.contains(&&k[..])) {
+            return Err(format!("{}: unknown key", key));
+        }
+
+        let mut keys = BTreeMap::new();
+        if let Some(k) = t.get("keys") {
+            for (action, key) in try!(k.as_table().ok_or_else(|| "keys: not a table".to_string())) {
+                let key = try!(key.as_str().ok_or_else(|| format!("keys.{}: not a string", action)));
+                let mut chars = key.chars();
+                let key = match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => return Err(format!("keys.{}: {:?} is not a single character", action, key)),
+                };
+                try!(KeyBindings::default().set(action, key).map_err(|e| format!("keys.{}: {}", action, e)));
+                keys.insert(action.clone(), key);
+            }
+        }
+
+        Ok(Profile {
+            url: try!(get_str(t, "url").and_then(|u| match u {
+                Some(u) => Url::parse(&u).map(Some).map_err(|e| format!("url: {}", e)),
+                None => Ok(None),
+            })),
+            user: try!(get_str(t, "user")),
+            password: try!(get_str(t, "password")),
+            token: try!(get_str(t, "token")),
+            credentials: try!(get_str(t, "credentials")),
+            ca_bundle: try!(get_str(t, "ca_bundle")).map(|p| expand_path(&p)),
+            client_cert: try!(get_str(t, "client_cert")).map(|p| expand_path(&p)),
+            client_cert_password: try!(get_str(t, "client_cert_password")),
+            insecure: try!(get(t, "insecure", "a boolean", Value::as_bool)),
+            proxy: try!(get_str(t, "proxy")),
+            timeout: try!(get_count(t, "timeout")),
+            retries: try!(get_count(t, "retries")).map(|r| r as usize),
+            retry_backoff: try!(get_count(t, "retry_backoff")),
+            max_redirects: try!(get_count(t, "max_redirects")).map(|r| r as usize),
+            download_dir: try!(get_str(t, "download_dir")).map(|p| expand_path(&p)),
+            sort: try!(get_str(t, "sort").and_then(|s| match s {
+                Some(s) => s.parse().map(Some).map_err(|e| format!("sort: {}", e)),
+                None => Ok(None),
+            })),
+            keys: keys,
+        })
+    }
+
+    /// Fill in values unspecified in this profile from `other`.
+    pub fn or(self, other: Profile) -> Profile {
+        let mut keys = other.keys;
+        keys.extend(self.keys);
+
+        Profile {
+            url: self.url.or(other.url),
+            user: self.user.or(other.user),
+            password: self.password.or(other.password),
+            token: self.token.or(other.token),
+            credentials: self.credentials.or(other.credentials),
+            ca_bundle: self.ca_bundle.or(other.ca_bundle),
+            client_cert: self.client_cert.or(other.client_cert),
+            client_cert_password: self.client_cert_password.or(other.client_cert_password),
+            insecure: self.insecure.or(other.insecure),
+            proxy: self.proxy.or(other.proxy),
+            timeout: self.timeout.or(other.timeout),
+            retries: self.retries.or(other.retries),
+            retry_backoff: self.retry_backoff.or(other.retry_backoff),
+            max_redirects: self.max_redirects.or(other.max_redirects),
+            download_dir: self.download_dir.or(other.download_dir),
+            sort: self.sort.or(other.sort),
+            keys: keys,
+        }
+    }
+}
+
+
+fn get<T, F: Fn(&Value) -> Option<T>>(t: &Table, key: &str, tp: &str, f: F) -> Result<Option<T>, String> {
+    match t.get(key) {
+        Some(v) => f(v).map(Some).ok_or_else(|| format!("{}: not {}", key, tp)),
+        None => Ok(None),
+    }
+}
+
+fn get_str(t: &Table, key: &str) -> Result<Option<String>, String> {
+    get(t, key, "a string", |v| v.as_str().map(str::to_string))
+}
+
+fn get_count(t: &Table, key: &str) -> Result<Option<u64>, String> {
+    get(t, key, "a nonnegative integer", |v| v.as_integer().and_then(|i| if i >= 0 { Some(i as u64) } else { None }))
+}
+
+fn expand_path(p: &str) -> PathBuf {
+    match (p.starts_with("~/"), home_dir()) {
+        (true, Some(home)) => home.join(&p[2..]),
+        _ => PathBuf::from(p),
+    }
+}
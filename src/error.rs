@@ -0,0 +1,105 @@
+//! Module containing the application's error type.
+
+
+use std::error::Error as StdError;
+use reqwest::{self, StatusCode, Url};
+use std::{io, fmt};
+
+
+/// Everything that can go wrong when talking to a server.
+///
+/// Each variant maps to a distinct process exit value, see [`exit_value()`](#method.exit_value).
+#[derive(Debug)]
+pub enum Error {
+    /// Couldn't connect to or communicate with the server.
+    Network(reqwest::Error),
+    /// The server returned a non-success status for the specified resource.
+    HttpStatus(Url, StatusCode),
+    /// The server's response for the specified resource isn't valid RFSAPI.
+    RfsapiParse(Url, reqwest::Error),
+    /// Local I/O failure.
+    Io(io::Error),
+    /// The server at the specified URL doesn't permit write requests.
+    WriteNotPermitted(Url),
+    /// The specified resource is a file where a directory was expected (`true`),
+    /// or a directory where a file was expected (`false`).
+    WrongKind(String, bool),
+    /// The specified amount of operations out of a batch failed.
+    Incomplete(usize),
+}
+
+impl Error {
+    /// Get the process exit value corresponding to this error.
+    ///
+    /// Variant|Value
+    /// -------|-----
+    /// `Network` | 2
+    /// `HttpStatus` | 3
+    /// `RfsapiParse` | 4
+    /// `Io` | 5
+    /// `WriteNotPermitted` | 6
+    /// `WrongKind` | 7
+    /// `Incomplete` | 8
+    pub fn exit_value(&self) -> i32 {
+        match *self {
+            Error::Network(_) => 2,
+            Error::HttpStatus(..) => 3,
+            Error::RfsapiParse(..) => 4,
+            Error::Io(_) => 5,
+            Error::WriteNotPermitted(_) => 6,
+            Error::WrongKind(..) => 7,
+            Error::Incomplete(_) => 8,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Network(ref e) => write!(f, "Network failure: {}", e),
+            Error::HttpStatus(ref u, s) => write!(f, "{} returned {}", u, s),
+            Error::RfsapiParse(ref u, ref e) => write!(f, "Couldn't parse response from {}: {}", u, e),
+            Error::Io(ref e) => write!(f, "I/O failure: {}", e),
+            Error::WriteNotPermitted(ref u) => write!(f, "Server at {} doesn't permit write requests", u),
+            Error::WrongKind(ref r, true) => write!(f, "{} is a file, not a directory", r),
+            Error::WrongKind(ref r, false) => write!(f, "{} is a directory, not a file", r),
+            Error::Incomplete(1) => write!(f, "1 operation failed"),
+            Error::Incomplete(n) => write!(f, "{} operations failed", n),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Network(_) => "network failure",
+            Error::HttpStatus(..) => "non-success status",
+            Error::RfsapiParse(..) => "invalid RFSAPI response",
+            Error::Io(_) => "I/O failure",
+            Error::WriteNotPermitted(_) => "writes not permitted",
+            Error::WrongKind(..) => "wrong resource kind",
+            Error::Incomplete(_) => "operations failed",
+        }
+    }
+
+    fn cause(&self) -> Option<&StdError> {
+        match *self {
+            Error::Network(ref e) |
+            Error::RfsapiParse(_, ref e) => Some(e),
+            Error::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Error {
+        Error::Network(e)
+    }
+}
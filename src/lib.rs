@@ -4,16 +4,26 @@ pub extern crate rfsapi;
 extern crate lazy_static;
 extern crate itertools;
 extern crate tabwriter;
+extern crate term_size;
 extern crate reqwest;
 extern crate getch;
+extern crate atty;
 #[macro_use]
 extern crate clap;
 extern crate time;
+extern crate toml;
 extern crate url;
+extern crate filetime;
+#[cfg(not(target_os = "windows"))]
+extern crate libc;
 
+mod error;
+mod config;
 mod options;
 
 pub mod ops;
 pub mod util;
 
-pub use self::options::Options;
+pub use self::error::Error;
+pub use self::config::{Config, Profile};
+pub use self::options::{Options, Command};
@@ -1,8 +1,5 @@
-extern crate term_size;
-extern crate getch;
 extern crate doh;
 
-use getch::Getch;
 use std::process::exit;
 use std::io::{Write, stdout, stderr};
 
@@ -23,15 +20,41 @@ fn handler_main() -> i32 {
 
 fn real_main() -> Result<(), (String, i32)> {
     let opts = doh::Options::parse();
-    let termsize = try!(term_size::dimensions().ok_or_else(|| ("Unknown terminal dimensions.".to_string(), 1)));
+    let session = try!(doh::ops::Session::new(&opts.session).map_err(|e| (format!("{}.", e), e.exit_value())));
+    let result = match opts.command {
+        doh::Command::Browse => return browse(opts, session),
+        doh::Command::List => doh::ops::cli::list(&session, &mut stdout(), opts.remote_dir),
+        doh::Command::Get { destination } => {
+            let destination = match (destination, opts.list.download_dir) {
+                (None, Some(dir)) => Some(dir.join(doh::ops::cli::default_destination(&opts.remote_dir))),
+                (destination, _) => destination,
+            };
+            doh::ops::cli::get(&session, &mut stdout(), opts.remote_dir, destination)
+        }
+        doh::Command::Put { local } => doh::ops::cli::put(&session, &mut stdout(), &local, opts.remote_dir),
+        doh::Command::Remove { targets, recursive, dry_run } => doh::ops::cli::rm(&session, &mut stdout(), &targets, recursive, dry_run),
+        doh::Command::Cat { targets, range } => {
+            doh::ops::cli::cat(&session, &mut stdout(), &targets, range.and_then(|r| doh::util::parse_byte_range(&r)))
+        }
+        doh::Command::Mirror { local, delete } => doh::ops::cli::mirror(&session, &mut stdout(), opts.remote_dir, &local, delete),
+        doh::Command::Push { local, delete } => doh::ops::cli::push(&session, &mut stdout(), &local, opts.remote_dir, delete),
+    };
+    result.map_err(|e| (format!("{}.", e), e.exit_value()))
+}
+
+fn browse(opts: doh::Options, session: doh::ops::Session) -> Result<(), (String, i32)> {
+    let termsize = try!(doh::ops::term::TermSize::new().ok_or_else(|| ("Unknown terminal dimensions.".to_string(), 1)));
+    let _screen = doh::util::RaiiGuard::new(|| print!("{}", doh::ops::term::alternate_screen(true)),
+                                            || {
+                                                print!("{}", doh::ops::term::alternate_screen(false));
+                                                let _ = stdout().flush();
+                                            });
     let _cursor = doh::util::RaiiGuard::new(|| print!("{}", doh::ops::term::show_cursor(false)),
                                             || print!("{}", doh::ops::term::show_cursor(true)));
 
-    let input = Getch::new();
-    let mut ctx = doh::ops::ListContext::new(opts.remote_dir.clone());
-    while try!(ctx.one_loop(&mut stdout(), &input, termsize).map_err(|e| (format!("Listing failure: {}", e), 3))) {
-        println!();
-    }
+    let input = doh::ops::input::Input::new();
+    let mut ctx = doh::ops::ListContext::new(opts.remote_dir, session, opts.list);
+    while try!(ctx.one_loop(&mut stdout(), &input, &termsize).map_err(|e| (format!("Listing failure: {}.", e), e.exit_value()))) {}
 
     Ok(())
 }
@@ -0,0 +1,107 @@
+//! Named remote directories, persisted as a TOML table of names to URLs in `bookmarks.toml` in the
+//! [config directory](../../util/fn.config_dir.html).
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use doh::ops::bookmarks::Bookmarks;
+//! let path = Bookmarks::default_path().unwrap();
+//! let mut bookmarks = Bookmarks::load(&path).unwrap();
+//! bookmarks.insert("work".to_string(), "https://files.example.com/shared/".parse().unwrap());
+//! bookmarks.save(&path).unwrap();
+//! ```
+
+
+use self::super::super::util::config_dir;
+use std::io::{self, Write, Read};
+use std::collections::{btree_map, BTreeMap};
+use std::path::{PathBuf, Path};
+use std::fs::{self, File};
+use toml::Value;
+use reqwest::Url;
+
+
+/// A set of bookmarked remote directories, by name.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+pub struct Bookmarks {
+    marks: BTreeMap<String, Url>,
+}
+
+impl Bookmarks {
+    /// Get the path to the default bookmarks file, if the config directory is known.
+    pub fn default_path() -> Option<PathBuf> {
+        config_dir().map(|c| c.join("bookmarks.toml"))
+    }
+
+    /// Read bookmarks from the specified file.
+    ///
+    /// A nonexistant file has no bookmarks.
+    pub fn load(p: &Path) -> io::Result<Bookmarks> {
+        let mut data = String::new();
+        match File::open(p) {
+            Ok(mut f) => {
+                try!(f.read_to_string(&mut data));
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        Bookmarks::parse(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", p.display(), e)))
+    }
+
+    /// Parse bookmarks from TOML.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use doh::ops::bookmarks::Bookmarks;
+    /// let bookmarks = Bookmarks::parse("work = \"https://files.example.com/\"").unwrap();
+    /// assert_eq!(bookmarks.get("work"), Some(&"https://files.example.com/".parse().unwrap()));
+    /// assert!(Bookmarks::parse("work = 1").is_err());
+    /// ```
+    pub fn parse(data: &str) -> Result<Bookmarks, String> {
+        let root = try!(data.parse::<Value>().map_err(|e| e.to_string()));
+        let mut marks = BTreeMap::new();
+        for (name, u) in try!(root.as_table().ok_or_else(|| "not a table".to_string())) {
+            let u = try!(u.as_str().ok_or_else(|| format!("{}: not a string", name)));
+            marks.insert(name.clone(), try!(Url::parse(u).map_err(|e| format!("{}: {}", name, e))));
+        }
+        Ok(Bookmarks { marks: marks })
+    }
+
+    /// Write the bookmarks to the specified file, creating its directory if needed.
+    pub fn save(&self, p: &Path) -> io::Result<()> {
+        if let Some(dir) = p.parent() {
+            try!(fs::create_dir_all(dir));
+        }
+
+        let table = self.marks.iter().map(|(name, u)| (name.clone(), Value::String(u.to_string()))).collect();
+        try!(try!(File::create(p)).write_all(Value::Table(table).to_string().as_bytes()));
+        Ok(())
+    }
+
+    /// Get the bookmark with the specified name.
+    pub fn get(&self, name: &str) -> Option<&Url> {
+        self.marks.get(name)
+    }
+
+    /// Add or replace the bookmark with the specified name.
+    pub fn insert(&mut self, name: String, u: Url) {
+        self.marks.insert(name, u);
+    }
+
+    /// Iterate over all bookmarks in name order.
+    pub fn iter(&self) -> btree_map::Iter<String, Url> {
+        self.marks.iter()
+    }
+
+    /// Get the amount of bookmarks.
+    pub fn len(&self) -> usize {
+        self.marks.len()
+    }
+
+    /// Check whether there are no bookmarks.
+    pub fn is_empty(&self) -> bool {
+        self.marks.is_empty()
+    }
+}
@@ -0,0 +1,611 @@
+//! Non-interactive operations, suitable for use from scripts.
+//!
+//! Nothing here reads from the keyboard or moves the cursor.
+
+
+use self::super::super::util::{percent_decode, directory_url, url_file_name};
+use self::super::super::Error;
+use reqwest::header::{ByteRangeSpec, ContentLength};
+use reqwest::{StatusCode, Response, Url};
+use std::io::{self, Write, Read, stdout};
+use std::path::{PathBuf, Path};
+use super::{RemoteFile, Session};
+use rfsapi::FilesetData;
+use filetime::{self, FileTime};
+use tabwriter::TabWriter;
+use std::collections::{VecDeque, BTreeSet, BTreeMap};
+use std::time::UNIX_EPOCH;
+use std::fs::{self, Metadata, File};
+use std::ffi::OsString;
+use std::cmp;
+
+
+/// Print the listing of the remote directory at `u` to `out`, in the same format as the interactive one.
+///
+/// The special `"../"` entry is omitted.
+pub fn list<W: Write>(session: &Session, out: &mut W, u: Url) -> Result<(), Error> {
+    let data = try!(fetch_listing(session, u.clone()));
+    if data.is_file {
+        return Err(Error::WrongKind(u.to_string(), true));
+    }
+
+    let mut tout = TabWriter::new(out);
+    for f in RemoteFile::from_response(data).into_iter().filter(|f| f.full_name != "../") {
+        try!(writeln!(tout, "{}", f));
+    }
+    try!(tout.flush());
+    Ok(())
+}
+
+/// Download the remote resource at `u` to `dest`, logging progress to `log`.
+///
+/// If `dest` is `None`, the resource is saved under its own name in the current directory.
+///
+/// If the resource is a file, it's saved to `dest`, or written to stdout if `dest` is `"-"`.
+///
+/// Interrupted downloads to files are resumed, see [`Session::download_resumable()`](../struct.Session.html#method.download_resumable).
+///
+/// If the resource is a directory, the entire tree under it is recreated under `dest`,
+/// with the files' modification times set to the remote ones.
+pub fn get<W: Write>(session: &Session, log: &mut W, u: Url, dest: Option<PathBuf>) -> Result<(), Error> {
+    let data = try!(fetch_listing(session, u.clone()));
+    let to_stdout = dest.as_ref().map(|d| d == Path::new("-")).unwrap_or(false);
+    let dest = dest.unwrap_or_else(|| default_destination(&u));
+
+    match (data.is_file, to_stdout) {
+        (true, true) => get_file(session, u, &mut stdout()),
+        (true, false) => {
+            try!(writeln!(log, "{}", dest.display()));
+            get_file_to(session, u, &dest)
+        }
+        (false, true) => Err(Error::WrongKind(u.to_string(), false)),
+        (false, false) => get_dir(session, log, directory_url(u), &dest, data),
+    }
+}
+
+/// Get the path `get()` saves the specified resource to by default: its name, or the host's for the root, in the current directory.
+pub fn default_destination(u: &Url) -> PathBuf {
+    PathBuf::from(url_file_name(u)
+        .or_else(|| u.host_str().map(str::to_string))
+        .unwrap_or_else(|| ".".to_string()))
+}
+
+fn get_file<W: Write>(session: &Session, u: Url, out: &mut W) -> Result<(), Error> {
+    let mut resp = try!(session.download_raw(u));
+    if !resp.status().is_success() {
+        return Err(Error::HttpStatus(resp.url().clone(), resp.status()));
+    }
+    try!(io::copy(&mut resp, out));
+    Ok(())
+}
+
+fn get_file_to(session: &Session, u: Url, dest: &Path) -> Result<(), Error> {
+    let status = try!(session.download_resumable(u.clone(), dest));
+    if status.is_success() {
+        Ok(())
+    } else {
+        Err(Error::HttpStatus(u, status))
+    }
+}
+
+fn get_dir<W: Write>(session: &Session, log: &mut W, u: Url, dest: &Path, data: FilesetData) -> Result<(), Error> {
+    try!(fs::create_dir_all(dest));
+    for f in RemoteFile::from_response(data) {
+        let subdest = match local_name(&f) {
+            Some(n) => dest.join(n),
+            None => continue,
+        };
+        let subu = entry_url(&u, &f);
+        if f.size.is_some() {
+            try!(writeln!(log, "{}", subdest.display()));
+            try!(get_file_to(session, subu, &subdest));
+            try!(set_modified(&subdest, &f));
+        } else {
+            let subdata = try!(fetch_listing(session, subu.clone()));
+            try!(get_dir(session, log, subu, &subdest, subdata));
+        }
+    }
+    Ok(())
+}
+
+/// Upload the local file or directory tree at `local` into the remote directory at `u`, logging each file's status to `log`.
+///
+/// The resource is uploaded under its own name, files in directories are `PUT` at their paths relative to it,
+/// which makes the server create the directory structure; empty directories are therefore skipped.
+///
+/// Failing uploads don't stop the others, but make the whole operation fail at the end.
+pub fn put<W: Write>(session: &Session, log: &mut W, local: &Path, u: Url) -> Result<(), Error> {
+    let data = try!(fetch_listing(session, u.clone()));
+    if data.is_file {
+        return Err(Error::WrongKind(u.to_string(), true));
+    }
+    if !data.writes_supported {
+        return Err(Error::WriteNotPermitted(u));
+    }
+
+    let local = try!(local.canonicalize());
+    let target = push_segment(&directory_url(u), &try!(path_name(&local)));
+    let failed = if local.is_dir() {
+        try!(put_dir(session, log, &local, directory_url(target)))
+    } else {
+        !try!(put_file(session, log, &local, target)).is_success() as usize
+    };
+
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(Error::Incomplete(failed))
+    }
+}
+
+fn put_file<W: Write>(session: &Session, log: &mut W, local: &Path, u: Url) -> Result<StatusCode, Error> {
+    let status = try!(session.upload(u.clone(), try!(File::open(local)))).status();
+    try!(writeln!(log, "{}\t{}", status, percent_decode(u.as_str()).unwrap()));
+    Ok(status)
+}
+
+fn put_dir<W: Write>(session: &Session, log: &mut W, local: &Path, u: Url) -> Result<usize, Error> {
+    let mut entries = try!(try!(fs::read_dir(local)).collect::<Result<Vec<_>, _>>());
+    entries.sort_by_key(|e| e.file_name());
+
+    let mut failed = 0;
+    for e in entries {
+        let path = e.path();
+        let subu = push_segment(&u, &try!(path_name(&path)));
+        if path.is_dir() {
+            failed += try!(put_dir(session, log, &path, directory_url(subu)));
+        } else {
+            failed += !try!(put_file(session, log, &path, subu)).is_success() as usize;
+        }
+    }
+    Ok(failed)
+}
+
+/// `DELETE` the remote resources at `targets`, logging each deletion's status to `log`.
+///
+/// Directories are only deleted if `recursive` is set, in which case their contents are deleted first, bottom-up;
+/// a directory is left alone if deleting anything inside it failed.
+///
+/// If `dry_run` is set, the resources that would be deleted are only logged.
+///
+/// Failing deletions don't stop the others, but make the whole operation fail at the end.
+pub fn rm<W: Write>(session: &Session, log: &mut W, targets: &[Url], recursive: bool, dry_run: bool) -> Result<(), Error> {
+    let mut failed = 0;
+    for u in targets {
+        let data = match fetch_listing(session, u.clone()) {
+            Ok(d) => d,
+            Err(e) => {
+                try!(writeln!(log, "{}.", e));
+                failed += 1;
+                continue;
+            }
+        };
+        if !dry_run && !data.writes_supported {
+            try!(writeln!(log, "{}.", Error::WriteNotPermitted(u.clone())));
+            failed += 1;
+        } else if data.is_file {
+            failed += !try!(rm_one(session, log, u.clone(), dry_run)) as usize;
+        } else if recursive {
+            failed += try!(rm_dir(session, log, directory_url(u.clone()), data, dry_run));
+        } else {
+            try!(writeln!(log, "{}.", Error::WrongKind(u.to_string(), false)));
+            failed += 1;
+        }
+    }
+
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(Error::Incomplete(failed))
+    }
+}
+
+fn rm_dir<W: Write>(session: &Session, log: &mut W, u: Url, data: FilesetData, dry_run: bool) -> Result<usize, Error> {
+    let mut failed = 0;
+    for f in RemoteFile::from_response(data).into_iter().filter(|f| f.full_name != "../") {
+        let subu = entry_url(&u, &f);
+        if f.size.is_some() {
+            failed += !try!(rm_one(session, log, subu, dry_run)) as usize;
+        } else {
+            match fetch_listing(session, subu.clone()) {
+                Ok(subdata) => failed += try!(rm_dir(session, log, subu, subdata, dry_run)),
+                Err(e) => {
+                    try!(writeln!(log, "{}.", e));
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    if failed == 0 {
+        failed += !try!(rm_one(session, log, u, dry_run)) as usize;
+    }
+    Ok(failed)
+}
+
+fn rm_one<W: Write>(session: &Session, log: &mut W, u: Url, dry_run: bool) -> Result<bool, Error> {
+    if dry_run {
+        try!(writeln!(log, "Would delete {}", percent_decode(u.as_str()).unwrap()));
+        Ok(true)
+    } else {
+        match session.delete(u.clone()) {
+            Ok(resp) => {
+                try!(writeln!(log, "{}\t{}", resp.status(), percent_decode(u.as_str()).unwrap()));
+                Ok(resp.status().is_success())
+            }
+            Err(e) => {
+                try!(writeln!(log, "Couldn't delete {}: {}.", percent_decode(u.as_str()).unwrap(), e));
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// Stream the remote files at `targets` to `out`, one after another.
+///
+/// If `range` is specified, only that range of bytes is written of each file; if the server ignores the requested range,
+/// the unwanted bytes are skipped locally.
+pub fn cat<W: Write>(session: &Session, out: &mut W, targets: &[Url], range: Option<ByteRangeSpec>) -> Result<(), Error> {
+    for u in targets {
+        let mut resp = try!(match range.clone() {
+            Some(r) => session.download_raw_range(u.clone(), r),
+            None => session.download_raw(u.clone()),
+        });
+        match (resp.status(), range.as_ref()) {
+            (s, _) if !s.is_success() => return Err(Error::HttpStatus(u.clone(), s)),
+            (StatusCode::PartialContent, _) |
+            (_, None) => try!(io::copy(&mut resp, out)),
+            (_, Some(r)) => try!(copy_range(&mut resp, out, r)),
+        };
+    }
+    Ok(())
+}
+
+/// Copy the specified `range` out of a full response.
+fn copy_range<W: Write>(resp: &mut Response, out: &mut W, range: &ByteRangeSpec) -> io::Result<u64> {
+    let (skip, take) = match *range {
+        ByteRangeSpec::FromTo(start, end) => (start, end - start + 1),
+        ByteRangeSpec::AllFrom(start) => (start, u64::max_value()),
+        ByteRangeSpec::Last(len) => {
+            match resp.headers().get::<ContentLength>() {
+                Some(&ContentLength(full)) => (full.saturating_sub(len), len),
+                // No way to know where the end is other than reaching it
+                None => return copy_tail(resp, out, len),
+            }
+        }
+    };
+
+    try!(io::copy(&mut resp.by_ref().take(skip), &mut io::sink()));
+    io::copy(&mut resp.take(take), out)
+}
+
+/// Copy the last `len` bytes of `reader` to `out`, only ever keeping those in memory.
+fn copy_tail<R: Read, W: Write>(reader: &mut R, out: &mut W, len: u64) -> io::Result<u64> {
+    let len = cmp::min(len, usize::max_value() as u64) as usize;
+    let mut tail = VecDeque::new();
+    let mut buf = [0; 8 * 1024];
+    loop {
+        let read = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        tail.extend(&buf[..read]);
+        let excess = tail.len().saturating_sub(len);
+        tail.drain(..excess);
+    }
+
+    let (front, back) = tail.as_slices();
+    try!(out.write_all(front));
+    try!(out.write_all(back));
+    Ok(tail.len() as u64)
+}
+
+/// Bring the local directory at `dest` up to date with the remote directory tree at `u`, logging each change to `log`,
+/// then a summary.
+///
+/// A remote file is downloaded if it's missing locally, or if its size differs, or if it was modified after the local one;
+/// downloaded files' modification times are set to the remote ones.
+///
+/// If `delete` is set, local files and directories not present remotely are deleted,
+/// except for partial downloads (`".part"` files) of remote files, which are resumed instead.
+///
+/// Failing transfers don't stop the others, but make the whole operation fail at the end.
+pub fn mirror<W: Write>(session: &Session, log: &mut W, u: Url, dest: &Path, delete: bool) -> Result<(), Error> {
+    let data = try!(fetch_listing(session, u.clone()));
+    if data.is_file {
+        return Err(Error::WrongKind(u.to_string(), true));
+    }
+
+    let mut stats = SyncStats::default();
+    try!(mirror_dir(session, log, directory_url(u), dest, data, delete, &mut stats));
+    stats.finish(log, "downloaded")
+}
+
+fn mirror_dir<W: Write>(session: &Session, log: &mut W, u: Url, dest: &Path, data: FilesetData, delete: bool, stats: &mut SyncStats)
+                        -> Result<(), Error> {
+    try!(fs::create_dir_all(dest));
+
+    let mut seen = BTreeSet::new();
+    for f in RemoteFile::from_response(data) {
+        let subdest = match local_name(&f) {
+            Some(n) => {
+                seen.insert(OsString::from(n));
+                dest.join(n)
+            }
+            None => continue,
+        };
+        let subu = entry_url(&u, &f);
+
+        let result = if f.size.is_some() {
+            if local_outdated(&f, &subdest) {
+                try!(writeln!(log, "{}", subdest.display()));
+                get_file_to(session, subu, &subdest).and_then(|_| set_modified(&subdest, &f)).map(|_| stats.transferred += 1)
+            } else {
+                stats.up_to_date += 1;
+                Ok(())
+            }
+        } else {
+            fetch_listing(session, subu.clone()).and_then(|subdata| mirror_dir(session, log, subu, &subdest, subdata, delete, stats))
+        };
+        if let Err(e) = result {
+            try!(writeln!(log, "{}.", e));
+            stats.failed += 1;
+        }
+    }
+
+    if delete {
+        for entry in try!(fs::read_dir(dest)) {
+            let entry = try!(entry);
+            let name = entry.file_name();
+            // Keep partial downloads of files still on the remote, so they can be resumed
+            let resumable = name.to_str().map(|n| n.ends_with(".part") && seen.contains(&OsString::from(&n[..n.len() - 5]))).unwrap_or(false);
+            if !seen.contains(&name) && !resumable {
+                let path = entry.path();
+                try!(writeln!(log, "Deleting {}", path.display()));
+                let removed = if path.is_dir() {
+                    fs::remove_dir_all(&path)
+                } else {
+                    fs::remove_file(&path)
+                };
+                match removed {
+                    Ok(()) => stats.deleted += 1,
+                    Err(e) => {
+                        try!(writeln!(log, "{}.", Error::Io(e)));
+                        stats.failed += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check whether the local copy of the specified remote file is missing, has a different size, or is older than it.
+pub fn local_outdated(remote: &RemoteFile, local: &Path) -> bool {
+    let meta = match fs::metadata(local) {
+        Ok(m) => m,
+        Err(_) => return true,
+    };
+    if Some(meta.len()) != remote.size {
+        return true;
+    }
+
+    match (remote.last_modified.as_ref(), modified_secs(&meta)) {
+        (Some(rm), Some(lm)) => rm.to_timespec().sec > lm,
+        _ => true,
+    }
+}
+
+
+/// Upload new and changed files from the local directory tree at `local` into the remote directory at `u`,
+/// logging each change to `log`, then a summary.
+///
+/// A local file is uploaded if it's missing remotely, or if its size differs, or if it was modified after the remote one.
+///
+/// If `delete` is set, remote files and directories not present locally are deleted, contents first.
+///
+/// Failing transfers don't stop the others, but make the whole operation fail at the end,
+/// unless the server refuses to write, which stops the operation immediately.
+pub fn push<W: Write>(session: &Session, log: &mut W, local: &Path, u: Url, delete: bool) -> Result<(), Error> {
+    let data = try!(fetch_listing(session, u.clone()));
+    if data.is_file {
+        return Err(Error::WrongKind(u.to_string(), true));
+    }
+    if !data.writes_supported {
+        return Err(Error::WriteNotPermitted(u));
+    }
+    if !local.is_dir() {
+        return Err(Error::WrongKind(local.display().to_string(), true));
+    }
+
+    let mut stats = SyncStats::default();
+    try!(push_dir(session, log, local, directory_url(u), Some(data), delete, &mut stats));
+    stats.finish(log, "uploaded")
+}
+
+fn push_dir<W: Write>(session: &Session, log: &mut W, local: &Path, u: Url, data: Option<FilesetData>, delete: bool,
+                      stats: &mut SyncStats)
+                      -> Result<(), Error> {
+    let mut remote: BTreeMap<String, RemoteFile> = data.map(RemoteFile::from_response)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|f| f.full_name != "../")
+        .map(|f| (f.full_name.clone(), f))
+        .collect();
+
+    let mut entries = try!(try!(fs::read_dir(local)).collect::<Result<Vec<_>, _>>());
+    entries.sort_by_key(|e| e.file_name());
+    for e in entries {
+        let path = e.path();
+        let name = try!(path_name(&path));
+        let subu = push_segment(&u, &name);
+
+        if path.is_dir() {
+            let subdata = match remote.remove(&format!("{}/", name)) {
+                Some(_) => {
+                    match fetch_listing(session, directory_url(subu.clone())) {
+                        Ok(d) => Some(d),
+                        Err(e) => {
+                            try!(writeln!(log, "{}.", e));
+                            stats.failed += 1;
+                            continue;
+                        }
+                    }
+                }
+                None => None,
+            };
+            try!(push_dir(session, log, &path, directory_url(subu), subdata, delete, stats));
+        } else if remote_outdated(&path, remote.remove(&name).as_ref()) {
+            let status = try!(put_file(session, log, &path, subu));
+            if status == StatusCode::Forbidden || status == StatusCode::MethodNotAllowed {
+                return Err(Error::WriteNotPermitted(u));
+            } else if status.is_success() {
+                stats.transferred += 1;
+            } else {
+                stats.failed += 1;
+            }
+        } else {
+            stats.up_to_date += 1;
+        }
+    }
+
+    if delete {
+        for f in remote.values() {
+            let delu = entry_url(&u, f);
+            let failed = if f.size.is_some() {
+                !try!(rm_one(session, log, delu, false)) as usize
+            } else {
+                match fetch_listing(session, delu.clone()) {
+                    Ok(deldata) => try!(rm_dir(session, log, delu, deldata, false)),
+                    Err(e) => {
+                        try!(writeln!(log, "{}.", e));
+                        1
+                    }
+                }
+            };
+            if failed == 0 {
+                stats.deleted += 1;
+            } else {
+                stats.failed += failed;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check whether the remote copy of the specified local file is missing, has a different size, or is older than it.
+pub fn remote_outdated(local: &Path, remote: Option<&RemoteFile>) -> bool {
+    let remote = match remote {
+        Some(r) => r,
+        None => return true,
+    };
+    let meta = match fs::metadata(local) {
+        Ok(m) => m,
+        Err(_) => return true,
+    };
+    if Some(meta.len()) != remote.size {
+        return true;
+    }
+
+    match (remote.last_modified.as_ref(), modified_secs(&meta)) {
+        (Some(rm), Some(lm)) => lm > rm.to_timespec().sec,
+        _ => true,
+    }
+}
+
+/// Set the modification time of the local copy of the specified remote file to the remote one's, if known,
+/// so that it's not seen as changed by later `mirror()`s and `push()`es.
+fn set_modified(local: &Path, remote: &RemoteFile) -> Result<(), Error> {
+    if let Some(secs) = remote.last_modified.map(|lm| lm.to_timespec().sec) {
+        if secs >= 0 {
+            let mtime = FileTime::from_seconds_since_1970(secs as u64, 0);
+            let atime = try!(fs::metadata(local).map(|m| FileTime::from_last_access_time(&m)));
+            try!(filetime::set_file_times(local, atime, mtime));
+        }
+    }
+    Ok(())
+}
+
+/// Get the last modification time of a local file in seconds since the UNIX epoch.
+fn modified_secs(meta: &Metadata) -> Option<i64> {
+    meta.modified().ok().and_then(|m| m.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs() as i64)
+}
+
+
+/// Tally of what a synchronisation did.
+#[derive(Debug, Default)]
+struct SyncStats {
+    transferred: usize,
+    up_to_date: usize,
+    deleted: usize,
+    failed: usize,
+}
+
+impl SyncStats {
+    /// Log the summary, failing if anything failed.
+    fn finish<W: Write>(&self, log: &mut W, transferred_label: &str) -> Result<(), Error> {
+        try!(writeln!(log,
+                      "{} {}, {} up to date, {} deleted, {} failed.",
+                      self.transferred,
+                      transferred_label,
+                      self.up_to_date,
+                      self.deleted,
+                      self.failed));
+
+        if self.failed == 0 {
+            Ok(())
+        } else {
+            Err(Error::Incomplete(self.failed))
+        }
+    }
+}
+
+
+/// Get the URL of the entry named `name` in the directory at `u`, percent-encoding it as needed.
+fn push_segment(u: &Url, name: &str) -> Url {
+    let mut u = u.clone();
+    u.path_segments_mut().unwrap().pop_if_empty().push(name);
+    u
+}
+
+/// Get the URL of the specified entry of the directory at `u`, with a trailing slash for directories.
+///
+/// Unlike `Url::join()`, this doesn't interpret names containing `#`, `?` or `:` as anything but a path segment.
+fn entry_url(u: &Url, f: &RemoteFile) -> Url {
+    let subu = push_segment(u, f.full_name.trim_right_matches('/'));
+    if f.size.is_some() { subu } else { directory_url(subu) }
+}
+
+/// Get the UTF-8 name of the last component of the specified path.
+fn path_name(p: &Path) -> Result<String, Error> {
+    p.file_name()
+        .and_then(|n| n.to_str())
+        .map(str::to_string)
+        .ok_or_else(|| Error::Io(io::Error::new(io::ErrorKind::InvalidData, format!("{} doesn't have a valid UTF-8 name", p.display()))))
+}
+
+/// Get the name to save the specified entry under locally, or `None` if it'd escape the directory it's in.
+///
+/// This also filters out the special `"../"` entry.
+fn local_name(f: &RemoteFile) -> Option<&str> {
+    let name = f.full_name.trim_right_matches('/');
+    if name.is_empty() || name == "." || name == ".." || name.contains(&['/', '\\'][..]) {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+/// GET the RFSAPI listing of the specified resource.
+pub fn fetch_listing(session: &Session, u: Url) -> Result<FilesetData, Error> {
+    let mut resp = try!(session.download(u));
+    let u = resp.url().clone();
+    if !resp.status().is_success() {
+        return Err(Error::HttpStatus(u, resp.status()));
+    }
+    resp.json().map_err(|e| Error::RfsapiParse(u, e))
+}
@@ -0,0 +1,133 @@
+//! Narrowing directory listings down to entries with names matching a pattern.
+//!
+//! # Examples
+//!
+//! ```
+//! # use doh::ops::filter::{Filter, FilterMode};
+//! let names = ["../", "Cargo.toml", "README.md", "src/"];
+//! let filter = Filter {
+//!     pattern: "r*.md".to_string(),
+//!     mode: FilterMode::Glob,
+//! };
+//! assert_eq!(names.iter().filter(|n| filter.matches(n)).collect::<Vec<_>>(), vec![&"README.md"]);
+//! ```
+
+
+use std::fmt;
+
+
+/// How a [`Filter`](struct.Filter.html)'s pattern is matched against names, always case-insensitively.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum FilterMode {
+    /// The name contains the pattern.
+    Substring,
+    /// The whole name matches the pattern, in which `*` matches any amount of characters, and `?` any single one.
+    Glob,
+    /// The name contains the pattern's characters in order, but not necessarily next to one another.
+    Fuzzy,
+}
+
+impl FilterMode {
+    /// Get the mode following this one, wrapping around.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use doh::ops::filter::FilterMode;
+    /// assert_eq!(FilterMode::Substring.next(), FilterMode::Glob);
+    /// assert_eq!(FilterMode::Fuzzy.next(), FilterMode::Substring);
+    /// ```
+    pub fn next(self) -> FilterMode {
+        match self {
+            FilterMode::Substring => FilterMode::Glob,
+            FilterMode::Glob => FilterMode::Fuzzy,
+            FilterMode::Fuzzy => FilterMode::Substring,
+        }
+    }
+}
+
+impl Default for FilterMode {
+    fn default() -> FilterMode {
+        FilterMode::Substring
+    }
+}
+
+impl fmt::Display for FilterMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            FilterMode::Substring => "substring",
+            FilterMode::Glob => "glob",
+            FilterMode::Fuzzy => "fuzzy",
+        })
+    }
+}
+
+
+/// A pattern names are matched against.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+pub struct Filter {
+    /// Pattern to match, an empty one matches all names.
+    pub pattern: String,
+    /// How to match the pattern.
+    pub mode: FilterMode,
+}
+
+impl Filter {
+    /// Check whether the specified name matches this filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use doh::ops::filter::{Filter, FilterMode};
+    /// let filter = |pattern: &str, mode| Filter { pattern: pattern.to_string(), mode: mode };
+    ///
+    /// assert!(filter("read", FilterMode::Substring).matches("README.md"));
+    /// assert!(!filter("rdm", FilterMode::Substring).matches("README.md"));
+    ///
+    /// assert!(filter("*.MD", FilterMode::Glob).matches("README.md"));
+    /// assert!(filter("re?d*", FilterMode::Glob).matches("README.md"));
+    /// assert!(!filter("read", FilterMode::Glob).matches("README.md"));
+    ///
+    /// assert!(filter("rdm", FilterMode::Fuzzy).matches("README.md"));
+    /// assert!(!filter("mdr", FilterMode::Fuzzy).matches("README.md"));
+    /// ```
+    pub fn matches(&self, name: &str) -> bool {
+        if self.pattern.is_empty() {
+            return true;
+        }
+
+        let pattern = self.pattern.to_lowercase();
+        let name = name.to_lowercase();
+        match self.mode {
+            FilterMode::Substring => name.contains(&pattern),
+            FilterMode::Glob => glob_match(&pattern.chars().collect::<Vec<_>>(), &name.chars().collect::<Vec<_>>()),
+            FilterMode::Fuzzy => {
+                let mut name = name.chars();
+                pattern.chars().all(|p| name.any(|n| n == p))
+            }
+        }
+    }
+}
+
+
+fn glob_match(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // Pattern position after the last star, and name position it's currently matched up to
+    let mut star = None;
+    while n < name.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            p += 1;
+            star = Some((p, n));
+        } else if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p;
+            n = star_n + 1;
+            star = Some((star_p, n));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,102 @@
+//! Browser-like navigation history.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate reqwest;
+//! # extern crate doh;
+//! # use doh::ops::history::{History, Visit};
+//! # use reqwest::Url;
+//! # fn main() {
+//! let root: Url = "http://example.com/".parse().unwrap();
+//! let sub: Url = "http://example.com/sub/".parse().unwrap();
+//! let mut history = History::new(&root);
+//!
+//! history.visit(Visit { url: root.clone(), selected: 3 }, &sub);
+//! assert_eq!(history.back(Visit { url: sub.clone(), selected: 0 }), Some(Visit { url: root.clone(), selected: 3 }));
+//! assert_eq!(history.forward(Visit { url: root.clone(), selected: 3 }), Some(Visit { url: sub.clone(), selected: 0 }));
+//! assert_eq!(history.forward(Visit { url: sub.clone(), selected: 0 }), None);
+//! assert_eq!(history.recent().collect::<Vec<_>>(), vec![&sub, &root]);
+//! # }
+//! ```
+
+
+use std::iter::Rev;
+use std::slice;
+use reqwest::Url;
+
+
+/// Maximal amount of recent locations remembered.
+pub const MAX_RECENT: usize = 20;
+
+
+/// A visited location along with the entry selected in it.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Visit {
+    /// The visited directory.
+    pub url: Url,
+    /// Index of the entry selected in it.
+    pub selected: usize,
+}
+
+/// Back and forward stacks of visited locations, and the most recently visited unique locations.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct History {
+    back: Vec<Visit>,
+    forward: Vec<Visit>,
+    recent: Vec<Url>,
+}
+
+impl History {
+    /// Start a history at the specified location.
+    pub fn new(start: &Url) -> History {
+        History {
+            back: vec![],
+            forward: vec![],
+            recent: vec![start.clone()],
+        }
+    }
+
+    /// Record leaving `from` for `to`, dropping the forward history.
+    pub fn visit(&mut self, from: Visit, to: &Url) {
+        self.back.push(from);
+        self.forward.clear();
+        self.remember(to);
+    }
+
+    /// Go back from `current`, returning the previous location, if any.
+    pub fn back(&mut self, current: Visit) -> Option<Visit> {
+        self.back.pop().map(|prev| {
+            self.forward.push(current);
+            self.remember(&prev.url);
+            prev
+        })
+    }
+
+    /// Go forward from `current`, returning the next location, if any.
+    pub fn forward(&mut self, current: Visit) -> Option<Visit> {
+        self.forward.pop().map(|next| {
+            self.back.push(current);
+            self.remember(&next.url);
+            next
+        })
+    }
+
+    /// Get the previous location without going back to it.
+    pub fn peek_back(&self) -> Option<&Visit> {
+        self.back.last()
+    }
+
+    /// Iterate over the most recently visited unique locations, most recent first.
+    pub fn recent(&self) -> Rev<slice::Iter<Url>> {
+        self.recent.iter().rev()
+    }
+
+    fn remember(&mut self, u: &Url) {
+        self.recent.retain(|r| r != u);
+        self.recent.push(u.clone());
+        if self.recent.len() > MAX_RECENT {
+            self.recent.remove(0);
+        }
+    }
+}
@@ -0,0 +1,484 @@
+//! Keyboard input, decoded from raw terminal bytes into logical key presses.
+//!
+//! On Windows, special keys arrive as a `224` byte followed by a scan code (`getch()` swallows `0`-prefixed ones),
+//! and Escape is always a lone Escape.
+//! Elsewhere, they arrive as ANSI/xterm escape sequences (like `ESC [ A` for Up Arrow),
+//! and a lone Escape is told apart from the start of a sequence by no further bytes arriving within
+//! [`ESCAPE_TIMEOUT_MS`](constant.ESCAPE_TIMEOUT_MS.html).
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use doh::ops::input::{Input, Key};
+//! let input = Input::new();
+//! loop {
+//!     let press = input.read_key().unwrap();
+//!     println!("{:?}", press);
+//!     if press.key == Key::Escape {
+//!         break;
+//!     }
+//! }
+//! ```
+
+
+use self::super::super::util::{GETCH_ENTER, GETCH_ESC, GETCH_BACKSPACE, GETCH_BACKSPACE_DEL, GETCH_SPECIAL_PREFIX, GETCH_ARROW_UP,
+                               GETCH_ARROW_DOWN, GETCH_ARROW_LEFT, GETCH_ARROW_RIGHT, GETCH_DELETE};
+use std::io::{self, Write, stderr};
+#[cfg(not(target_os = "windows"))]
+use self::super::term;
+use getch::Getch;
+#[cfg(not(target_os = "windows"))]
+use libc;
+#[cfg(not(target_os = "windows"))]
+use std::mem;
+
+
+/// Milliseconds to wait for the rest of an escape sequence before deciding Escape was pressed on its own.
+pub const ESCAPE_TIMEOUT_MS: u64 = 50;
+
+
+/// A logical key.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum Key {
+    /// A character key; for Ctrl+letter, the lowercase letter.
+    Char(char),
+    Enter,
+    Escape,
+    Backspace,
+    Tab,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    /// Function key with the specified number, starting at 1.
+    F(u8),
+    /// A sequence that couldn't be decoded.
+    Unknown,
+    /// Not a key: the terminal was resized while waiting for a key press, see [`term::watch_resize()`](../term/fn.watch_resize.html).
+    Resize,
+}
+
+/// A key press along with the modifiers held.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct KeyPress {
+    /// The pressed key.
+    pub key: Key,
+    /// Whether Ctrl was held.
+    pub ctrl: bool,
+    /// Whether Alt was held.
+    pub alt: bool,
+    /// Whether Shift was held, only reported for special keys.
+    pub shift: bool,
+}
+
+impl KeyPress {
+    /// A press of the specified key without modifiers.
+    pub fn plain(key: Key) -> KeyPress {
+        KeyPress {
+            key: key,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        }
+    }
+
+    /// Check whether this is Ctrl+C, which is read as a key press instead of interrupting the process.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use doh::ops::input::{KeyPress, Key};
+    /// assert!(KeyPress { ctrl: true, ..KeyPress::plain(Key::Char('c')) }.is_interrupt());
+    /// assert!(!KeyPress::plain(Key::Char('c')).is_interrupt());
+    /// ```
+    pub fn is_interrupt(&self) -> bool {
+        self.key == Key::Char('c') && self.ctrl && !self.alt
+    }
+
+    /// Check whether this is the specified character key, ignoring case, without Ctrl or Alt.
+    pub fn is_char(&self, c: char) -> bool {
+        match self.key {
+            Key::Char(k) => !self.ctrl && !self.alt && k.to_lowercase().eq(c.to_lowercase()),
+            _ => false,
+        }
+    }
+
+    /// Check whether this is exactly the specified character key, without Ctrl or Alt.
+    pub fn is_exact_char(&self, c: char) -> bool {
+        self.key == Key::Char(c) && !self.ctrl && !self.alt
+    }
+
+    /// Set modifiers from an xterm modifier parameter (`1` + Shift `1` + Alt `2` + Ctrl `4`).
+    fn with_xterm_modifiers(mut self, param: u32) -> KeyPress {
+        let mods = param.saturating_sub(1);
+        self.shift |= mods & 1 != 0;
+        self.alt |= mods & 2 != 0;
+        self.ctrl |= mods & 4 != 0;
+        self
+    }
+}
+
+
+/// Terminal keyboard input, unbuffered and unechoed for as long as it exists.
+///
+/// While waiting for a key press, Ctrl+C doesn't send `SIGINT`, but is decoded like any other key,
+/// so that the reader can restore the terminal before quitting;
+/// outside of that, Ctrl+C and Ctrl+Z work as usual, so e.g. a hung request can still be interrupted.
+pub struct Input {
+    /// Keeps the terminal in raw mode, and reads from it on Windows.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    getch: Getch,
+}
+
+impl Input {
+    /// Start reading from the terminal.
+    pub fn new() -> Input {
+        Input { getch: Getch::new() }
+    }
+
+    /// Read the next key press, waiting for one.
+    ///
+    /// Returns [`Key::Resize`](enum.Key.html#variant.Resize) if the terminal is resized while waiting.
+    pub fn read_key(&self) -> io::Result<KeyPress> {
+        let _signals = SignalsDisabled::new();
+        let first = match try!(self.read_first_byte()) {
+            Some(b) => b,
+            None => return Ok(KeyPress::plain(Key::Resize)),
+        };
+        decode_key(first, |wait| if wait {
+            self.read_byte().map(Some)
+        } else {
+            self.read_byte_timeout(ESCAPE_TIMEOUT_MS)
+        })
+    }
+
+    /// Read a line of input until Enter is pressed, optionally echoing it to stderr.
+    ///
+    /// Backspace removes the last character, other special keys are ignored.
+    ///
+    /// Returns `Ok(None)` if Escape or Ctrl+C was pressed instead.
+    pub fn read_line(&self, echo: bool) -> io::Result<Option<String>> {
+        let _signals = SignalsDisabled::new();
+        let mut err = stderr();
+        let mut line = String::new();
+        loop {
+            let press = try!(self.read_key());
+            match press.key {
+                Key::Enter => break,
+                Key::Escape => {
+                    try!(writeln!(err));
+                    return Ok(None);
+                }
+                _ if press.is_interrupt() => {
+                    try!(writeln!(err));
+                    return Ok(None);
+                }
+                Key::Backspace => {
+                    if line.pop().is_some() && echo {
+                        try!(write!(err, "\x08 \x08"));
+                        try!(err.flush());
+                    }
+                }
+                Key::Char(c) if !press.ctrl && !press.alt => {
+                    line.push(c);
+                    if echo {
+                        try!(write!(err, "{}", c));
+                        try!(err.flush());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        try!(writeln!(err));
+        Ok(Some(line))
+    }
+
+    #[cfg(target_os = "windows")]
+    fn read_byte(&self) -> io::Result<u8> {
+        self.getch.getch()
+    }
+
+    #[cfg(target_os = "windows")]
+    fn read_first_byte(&self) -> io::Result<Option<u8>> {
+        self.read_byte().map(Some)
+    }
+
+    /// The console never sends escape sequences, so there's nothing to wait for after an Escape.
+    #[cfg(target_os = "windows")]
+    fn read_byte_timeout(&self, _: u64) -> io::Result<Option<u8>> {
+        Ok(None)
+    }
+
+    /// Read straight from the file descriptor, since buffering in `stdin()` would hide pending bytes from `poll()`.
+    #[cfg(not(target_os = "windows"))]
+    fn read_byte_once(&self) -> io::Result<u8> {
+        let mut b = 0u8;
+        match unsafe { libc::read(libc::STDIN_FILENO, &mut b as *mut u8 as *mut libc::c_void, 1) } {
+            1 => Ok(b),
+            0 => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stdin closed")),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn read_byte(&self) -> io::Result<u8> {
+        loop {
+            match self.read_byte_once() {
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                r => return r,
+            }
+        }
+    }
+
+    /// Like `read_byte()`, but returns `None` if interrupted by the terminal being resized.
+    #[cfg(not(target_os = "windows"))]
+    fn read_first_byte(&self) -> io::Result<Option<u8>> {
+        loop {
+            match self.read_byte_once() {
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {
+                    if term::resized() {
+                        return Ok(None);
+                    }
+                }
+                r => return r.map(Some),
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn read_byte_timeout(&self, timeout_ms: u64) -> io::Result<Option<u8>> {
+        let mut fd = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        loop {
+            match unsafe { libc::poll(&mut fd, 1, timeout_ms as libc::c_int) } {
+                0 => return Ok(None),
+                r if r < 0 => {
+                    let err = io::Error::last_os_error();
+                    if err.kind() != io::ErrorKind::Interrupted {
+                        return Err(err);
+                    }
+                }
+                _ => return self.read_byte().map(Some),
+            }
+        }
+    }
+}
+
+
+/// Keeps the terminal from turning Ctrl+C and Ctrl+Z into signals, for as long as it exists.
+struct SignalsDisabled {
+    /// Terminal attributes to restore, if they could be read.
+    #[cfg(not(target_os = "windows"))]
+    previous: Option<libc::termios>,
+}
+
+impl SignalsDisabled {
+    #[cfg(not(target_os = "windows"))]
+    fn new() -> SignalsDisabled {
+        unsafe {
+            let mut attrs: libc::termios = mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut attrs) != 0 {
+                return SignalsDisabled { previous: None };
+            }
+            let previous = attrs;
+            attrs.c_lflag &= !libc::ISIG;
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &attrs);
+            SignalsDisabled { previous: Some(previous) }
+        }
+    }
+
+    /// The console delivers Ctrl+C to `getch()` already.
+    #[cfg(target_os = "windows")]
+    fn new() -> SignalsDisabled {
+        SignalsDisabled {}
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+impl Drop for SignalsDisabled {
+    fn drop(&mut self) {
+        if let Some(ref previous) = self.previous {
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, previous) };
+        }
+    }
+}
+
+
+/// Decode a key press starting with the byte `first`.
+///
+/// `next(wait)` returns the next byte; with `wait` being `false` it may instead return `None`
+/// if no byte arrives soon, which is used to tell a lone Escape from an escape sequence.
+///
+/// # Examples
+///
+/// ```
+/// # use doh::ops::input::{decode_key, KeyPress, Key};
+/// fn decode(bytes: &[u8]) -> KeyPress {
+///     let mut rest = bytes[1..].iter().cloned();
+///     decode_key(bytes[0], |_| Ok(rest.next())).unwrap()
+/// }
+///
+/// assert_eq!(decode(b"q"), KeyPress::plain(Key::Char('q')));
+/// assert_eq!(decode(b"\r"), KeyPress::plain(Key::Enter));
+/// assert_eq!(decode(b"\x1B"), KeyPress::plain(Key::Escape));
+/// assert_eq!(decode(b"\x7F"), KeyPress::plain(Key::Backspace));
+/// assert_eq!(decode("ą".as_bytes()), KeyPress::plain(Key::Char('ą')));
+/// assert_eq!(decode(b"\x03"), KeyPress { ctrl: true, ..KeyPress::plain(Key::Char('c')) });
+/// assert_eq!(decode(b"\x1Bx"), KeyPress { alt: true, ..KeyPress::plain(Key::Char('x')) });
+/// # if !cfg!(target_os = "windows") {
+/// assert_eq!(decode(b"\x1B[A"), KeyPress::plain(Key::Up));
+/// assert_eq!(decode(b"\x1BOB"), KeyPress::plain(Key::Down));
+/// assert_eq!(decode(b"\x1B[5~"), KeyPress::plain(Key::PageUp));
+/// assert_eq!(decode(b"\x1B[1;5C"), KeyPress { ctrl: true, ..KeyPress::plain(Key::Right) });
+/// assert_eq!(decode(b"\x1B[3~"), KeyPress::plain(Key::Delete));
+/// assert_eq!(decode(b"\x1B[H"), KeyPress::plain(Key::Home));
+/// assert_eq!(decode(b"\x1BOP"), KeyPress::plain(Key::F(1)));
+/// assert_eq!(decode(b"\x1B[24~"), KeyPress::plain(Key::F(12)));
+/// # } else {
+/// assert_eq!(decode(&[224, 72]), KeyPress::plain(Key::Up));
+/// assert_eq!(decode(&[224, 134]), KeyPress::plain(Key::F(12)));
+/// # }
+/// ```
+pub fn decode_key<F: FnMut(bool) -> io::Result<Option<u8>>>(first: u8, mut next: F) -> io::Result<KeyPress> {
+    macro_rules! next_byte {
+        () => {
+            match try!(next(true)) {
+                Some(b) => b,
+                None => return Ok(KeyPress::plain(Key::Unknown)),
+            }
+        }
+    }
+
+    Ok(match first {
+        GETCH_ENTER | b'\n' => KeyPress::plain(Key::Enter),
+        b'\t' => KeyPress::plain(Key::Tab),
+        GETCH_BACKSPACE | GETCH_BACKSPACE_DEL => KeyPress::plain(Key::Backspace),
+        GETCH_ESC => {
+            match try!(next(false)) {
+                None => KeyPress::plain(Key::Escape),
+                Some(b'[') if !cfg!(target_os = "windows") => decode_csi(&mut next),
+                Some(b'O') if !cfg!(target_os = "windows") => {
+                    let key = match next_byte!() {
+                        b'P' => Key::F(1),
+                        b'Q' => Key::F(2),
+                        b'R' => Key::F(3),
+                        b'S' => Key::F(4),
+                        b => final_byte_key(b),
+                    };
+                    KeyPress::plain(key)
+                }
+                Some(b'\x1B') => KeyPress { alt: true, ..KeyPress::plain(Key::Escape) },
+                Some(b) => {
+                    let mut press = try!(decode_key(b, next));
+                    press.alt = true;
+                    press
+                }
+            }
+        }
+        GETCH_SPECIAL_PREFIX if cfg!(target_os = "windows") => decode_scan_code(next_byte!()),
+        c @ 1...26 => {
+            KeyPress {
+                ctrl: true,
+                ..KeyPress::plain(Key::Char((b'a' + c - 1) as char))
+            }
+        }
+        b if b < 0x80 => KeyPress::plain(Key::Char(b as char)),
+        b => {
+            // UTF-8 sequence, the leading byte's high set bits specify its length
+            let len = (!b).leading_zeros() as usize;
+            if len > 4 {
+                return Ok(KeyPress::plain(Key::Unknown));
+            }
+            let mut bytes = vec![b];
+            for _ in 1..len {
+                bytes.push(next_byte!());
+            }
+            KeyPress::plain(String::from_utf8(bytes).ok().and_then(|s| s.chars().next()).map(Key::Char).unwrap_or(Key::Unknown))
+        }
+    })
+}
+
+/// Decode the rest of an `ESC [` control sequence: parameter bytes, then the final byte.
+fn decode_csi<F: FnMut(bool) -> io::Result<Option<u8>>>(next: &mut F) -> KeyPress {
+    let mut params = String::new();
+    let fin = loop {
+        match next(true) {
+            Ok(Some(b @ 0x30...0x3F)) => params.push(b as char),
+            Ok(Some(b)) => break b,
+            _ => return KeyPress::plain(Key::Unknown),
+        }
+    };
+    let mut params = params.split(';').map(|p| p.parse::<u32>().unwrap_or(1));
+    let first = params.next().unwrap_or(1);
+    let modifiers = params.next().unwrap_or(1);
+
+    let key = if fin == b'~' {
+        match first {
+            1 | 7 => Key::Home,
+            2 => Key::Insert,
+            3 => Key::Delete,
+            4 | 8 => Key::End,
+            5 => Key::PageUp,
+            6 => Key::PageDown,
+            11...15 => Key::F((first - 10) as u8),
+            17...21 => Key::F((first - 11) as u8),
+            23 | 24 => Key::F((first - 12) as u8),
+            _ => Key::Unknown,
+        }
+    } else if fin == b'Z' {
+        return KeyPress {
+            shift: true,
+            ..KeyPress::plain(Key::Tab)
+        };
+    } else {
+        final_byte_key(fin)
+    };
+    KeyPress::plain(key).with_xterm_modifiers(modifiers)
+}
+
+fn final_byte_key(b: u8) -> Key {
+    match b {
+        b'A' => Key::Up,
+        b'B' => Key::Down,
+        b'C' => Key::Right,
+        b'D' => Key::Left,
+        b'H' => Key::Home,
+        b'F' => Key::End,
+        _ => Key::Unknown,
+    }
+}
+
+/// Decode a Windows console scan code, as returned by `getch()` after `224`.
+fn decode_scan_code(code: u8) -> KeyPress {
+    let (key, ctrl) = match code {
+        GETCH_ARROW_UP => (Key::Up, false),
+        GETCH_ARROW_DOWN => (Key::Down, false),
+        GETCH_ARROW_LEFT => (Key::Left, false),
+        GETCH_ARROW_RIGHT => (Key::Right, false),
+        71 => (Key::Home, false),
+        79 => (Key::End, false),
+        73 => (Key::PageUp, false),
+        81 => (Key::PageDown, false),
+        82 => (Key::Insert, false),
+        GETCH_DELETE => (Key::Delete, false),
+        133 | 134 => (Key::F(code - 122), false),
+        141 => (Key::Up, true),
+        145 => (Key::Down, true),
+        115 => (Key::Left, true),
+        116 => (Key::Right, true),
+        119 => (Key::Home, true),
+        117 => (Key::End, true),
+        _ => (Key::Unknown, false),
+    };
+    KeyPress { ctrl: ctrl, ..KeyPress::plain(key) }
+}
@@ -1,52 +1,43 @@
-use self::super::util::{human_readable_size, percent_decode, parent_url, GETCH_SPECIAL_PREFIX, GETCH_ARROW_RIGHT, GETCH_ARROW_LEFT, GETCH_ARROW_DOWN,
-                        GETCH_ARROW_UP, TAB_SPACING, GETCH_DELETE, GETCH_ENTER, USER_AGENT, GETCH_ESC};
-use rfsapi::{RawFsApiHeader, FilesetData, RawFileData};
+use self::super::util::{human_readable_size, percent_decode, url_file_name, parent_url, TAB_SPACING};
 use std::io::{self, BufReader, BufRead, Write, Read};
-use reqwest::{ClientBuilder, Response, IntoUrl, Client, Url};
-use reqwest::header::UserAgent;
+use rfsapi::{FilesetData, RawFileData};
 use std::path::{PathBuf, Path};
+use std::collections::HashMap;
 use itertools::Itertools;
 use tabwriter::TabWriter;
+use self::super::Error;
+use self::history::{History, Visit};
+use self::bookmarks::Bookmarks;
+use self::filter::Filter;
+use self::input::{Input, Key};
+use self::term::TermSize;
+use std::str::FromStr;
 use std::{cmp, fmt};
 use std::fs::File;
-use getch::Getch;
+use reqwest::Url;
 use time::Tm;
 
-pub mod term;
-
-
-/// PUT a resource.
-pub fn upload<U: IntoUrl>(u: U, f: File) -> Response {
-    client().put(u).header(UserAgent::new(USER_AGENT)).body(f).send().unwrap()
-}
-
-/// DELETE a resource.
-pub fn delete<U: IntoUrl>(u: U) -> Response {
-    client().delete(u).header(UserAgent::new(USER_AGENT)).send().unwrap()
-}
-
-/// GET a resource with the RFSAPI header, auto-unpacking gzip.
-pub fn download<U: IntoUrl>(u: U) -> Response {
-    really_download(u, true)
-}
-
-/// GET a resource normally, auto-unpacking gzip.
-pub fn download_raw<U: IntoUrl>(u: U) -> Response {
-    really_download(u, false)
-}
+mod session;
 
-fn really_download<U: IntoUrl>(u: U, raw: bool) -> Response {
-    client().get(u).header(RawFsApiHeader(raw)).header(UserAgent::new(USER_AGENT)).send().unwrap()
-}
+pub mod bookmarks;
+pub mod filter;
+pub mod progress;
+pub mod input;
+pub mod history;
+pub mod netrc;
+pub mod proxy;
+pub mod term;
+pub mod cli;
 
-fn client() -> Client {
-    ClientBuilder::new().gzip(true).build().unwrap()
-}
+pub use self::session::{SessionConfig, Credentials, Session};
+pub use self::proxy::ProxyConfig;
 
 
 /// Copy `label`led data from `reader` to `writer`, stopping each screen, wrapping at `term_size`,
 /// polling `input` for instructions.
 ///
+/// When the terminal is resized, the current screen is wrapped anew and shown again.
+///
 /// Never panics, returns `Ok(true)` if paging succeeded or `Ok(false)` if paged data isn't UTF-8.
 ///
 /// ### Controls on nonlast pages
@@ -61,13 +52,12 @@ fn client() -> Client {
 /// Key|Result
 /// ---|------
 /// Any key | stop paging
-pub fn paging_copy<R: Read, W: Write>(reader: &mut R, writer: &mut W, label: &str, input: &Getch, term_size: (usize, usize)) -> io::Result<bool> {
+pub fn paging_copy<R: Read, W: Write>(reader: &mut R, writer: &mut W, label: &str, input: &Input, term_size: &TermSize) -> io::Result<bool> {
     /// Costant width of screen-end message for wrapping, as seen in-code from bottom right to top left.
     const END_MESSAGE_RAW_LEN: usize = 31 + 4 + 22 + 2 + 1 + 2 + 1;
 
-    let (tx, ty) = term_size;
     let mut reader = BufReader::new(reader);
-    let mut outlines: Vec<String> = vec![];
+    let mut lines: Vec<Vec<char>> = vec![];
 
     let mut line = String::new();
     while match reader.read_line(&mut line) {
@@ -80,48 +70,62 @@ pub fn paging_copy<R: Read, W: Write>(reader: &mut R, writer: &mut W, label: &st
             };
         }
     } {
-        line = line.replace(&['\r', '\n'][..], "").replace('\t', &TAB_SPACING);
-        if line.is_empty() {
-            outlines.push(String::new());
-        } else {
-            outlines.extend(line.drain(..).chunks(tx).into_iter().map(|l| l.collect()));
-        }
+        lines.push(line.replace(&['\r', '\n'][..], "").replace('\t', &TAB_SPACING).chars().collect());
+        line.clear();
     }
     drop(line);
 
+    if lines.is_empty() {
+        try!(writeln!(writer));
+        return Ok(true);
+    }
+
     let end_message_len = END_MESSAGE_RAW_LEN + label.chars().count();
-    let end_message_lines = (end_message_len as f64 / tx as f64).floor() as usize;
-    let screen_count = (outlines.len() as f64 / (ty - end_message_lines) as f64).ceil() as usize;
-    for (si, screen) in outlines.chunks(ty - end_message_lines).enumerate() {
-        if si != 0 {
+    // Line and character in it the current screen starts at, which survive rewrapping
+    let mut position = (0, 0);
+    let mut first_screen = true;
+    loop {
+        let (tx, ty) = term_size.get();
+        let tx = cmp::max(tx, 1);
+        let rows: Vec<(usize, usize)> = lines.iter()
+            .enumerate()
+            .flat_map(|(i, l)| (0..cmp::max((l.len() + tx - 1) / tx, 1)).map(move |r| (i, r * tx)))
+            .collect();
+        let height = cmp::max(ty.saturating_sub(end_message_len / tx), 1);
+
+        let first = rows.iter().position(|&(i, start)| i == position.0 && start + tx > position.1).unwrap_or(0);
+        let last = cmp::min(first + height, rows.len());
+
+        if !first_screen {
             try!(writeln!(writer));
         }
-        for line in screen {
-            try!(writeln!(writer, "{}", line));
+        for &(i, start) in &rows[first..last] {
+            try!(writeln!(writer, "{}", lines[i][start..cmp::min(start + tx, lines[i].len())].iter().cloned().collect::<String>()));
         }
 
         try!(write!(writer, "<{}> ", label));
-        if si == screen_count - 1 {
+        if last == rows.len() {
             try!(write!(writer, "<End of file> <Press any key to stop>"));
         } else {
             try!(write!(writer,
                         "<{}%> <Press any key for {} screen> <Press Escape to stop>",
-                        ((si + 1) as f64 / screen_count as f64 * 100f64).round(),
-                        if si + 1 == screen_count - 1 {
+                        (last as f64 / rows.len() as f64 * 100f64).round(),
+                        if last + height >= rows.len() {
                             "last"
                         } else {
                             "next"
                         }));
         }
         try!(writer.flush());
+        first_screen = false;
 
-        match try!(input.getch()) {
-            GETCH_ESC => break,
-            GETCH_SPECIAL_PREFIX => {
-                // Prevent doublescroll when using arrows
-                try!(input.getch());
-            }
-            _ => {}
+        let press = try!(input.read_key());
+        match press.key {
+            Key::Resize => {}
+            Key::Escape => break,
+            _ if press.is_interrupt() => break,
+            _ if last == rows.len() => break,
+            _ => position = rows[last],
         }
     }
 
@@ -130,22 +134,175 @@ pub fn paging_copy<R: Read, W: Write>(reader: &mut R, writer: &mut W, label: &st
 }
 
 
+/// Configuration of a [`ListContext`](struct.ListContext.html).
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+pub struct ListConfig {
+    /// Order to list entries in, or `None` for the server's order.
+    pub sort: Option<SortOrder>,
+    /// Keys performing actions.
+    pub keys: KeyBindings,
+    /// Directory the download file picker starts in, or `None` for the current directory.
+    pub download_dir: Option<PathBuf>,
+    /// File to keep bookmarks in, see [`Bookmarks`](bookmarks/struct.Bookmarks.html), or `None` to disable bookmarks.
+    pub bookmarks: Option<PathBuf>,
+}
+
+/// Listing order of directory entries.
+///
+/// Directories are always listed before files.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Alphabetically by name, ignoring case.
+    Name,
+    /// Largest first.
+    Size,
+    /// Most recently modified first.
+    Modified,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SortOrder, String> {
+        match s {
+            "name" => Ok(SortOrder::Name),
+            "size" => Ok(SortOrder::Size),
+            "modified" => Ok(SortOrder::Modified),
+            _ => Err(format!("{} is not one of name, size, modified", s)),
+        }
+    }
+}
+
+/// Keys performing listing actions, matched case-insensitively,
+/// except for `next_match` and `previous_match`, which by default differ only in case.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct KeyBindings {
+    /// End listing, `'q'` by default. Escape also always ends listing.
+    pub quit: char,
+    /// Download the highlighted file, `'d'` by default.
+    pub download: char,
+    /// Upload a file into the current directory, `'u'` by default.
+    pub upload: char,
+    /// Bookmark the current directory, `'b'` by default.
+    pub bookmark: char,
+    /// Show the bookmarks to go to one, `'g'` by default.
+    pub bookmarks: char,
+    /// Go back in history, `'['` by default.
+    pub back: char,
+    /// Go forward in history, `']'` by default.
+    pub forward: char,
+    /// Show the recently visited locations to go to one, `'h'` by default.
+    pub history: char,
+    /// Filter the entries, `'/'` by default.
+    pub filter: char,
+    /// Select the next entry matching the filter, `'n'` by default.
+    pub next_match: char,
+    /// Select the previous entry matching the filter, `'N'` by default.
+    pub previous_match: char,
+}
+
+impl KeyBindings {
+    /// Names of all actions, as accepted by [`set()`](#method.set).
+    pub const ACTIONS: &'static [&'static str] = &["quit",
+                                                   "download",
+                                                   "upload",
+                                                   "bookmark",
+                                                   "bookmarks",
+                                                   "back",
+                                                   "forward",
+                                                   "history",
+                                                   "filter",
+                                                   "next_match",
+                                                   "previous_match"];
+
+    /// Bind the specified action to the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use doh::ops::KeyBindings;
+    /// let mut keys = KeyBindings::default();
+    /// assert_eq!(keys.set("download", 's'), Ok(()));
+    /// assert_eq!(keys.download, 's');
+    /// assert!(keys.set("fly", 'f').is_err());
+    /// assert!(keys.set("quit", 'ą').is_err());
+    /// ```
+    pub fn set(&mut self, action: &str, key: char) -> Result<(), String> {
+        if !key.is_ascii() || key.is_ascii_control() {
+            return Err(format!("{:?} is not a printable ASCII character", key));
+        }
+
+        *match action {
+            "quit" => &mut self.quit,
+            "download" => &mut self.download,
+            "upload" => &mut self.upload,
+            "bookmark" => &mut self.bookmark,
+            "bookmarks" => &mut self.bookmarks,
+            "back" => &mut self.back,
+            "forward" => &mut self.forward,
+            "history" => &mut self.history,
+            "filter" => &mut self.filter,
+            "next_match" => &mut self.next_match,
+            "previous_match" => &mut self.previous_match,
+            _ => return Err(format!("{} is not one of {}", action, KeyBindings::ACTIONS.join(", "))),
+        } = key;
+        Ok(())
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        KeyBindings {
+            quit: 'q',
+            download: 'd',
+            upload: 'u',
+            bookmark: 'b',
+            bookmarks: 'g',
+            back: '[',
+            forward: ']',
+            history: 'h',
+            filter: '/',
+            next_match: 'n',
+            previous_match: 'N',
+        }
+    }
+}
+
+
 /// Main context used to list a server.
 pub struct ListContext {
+    session: Session,
+    config: ListConfig,
+    history: History,
     cururl: Url,
     files: Vec<RemoteFile>,
+    lines: Vec<String>,
     selected: usize,
+    top: usize,
+    filter: Option<Filter>,
+    status: Option<String>,
+    selections: HashMap<Url, String>,
+    reselect: Option<String>,
     have_write: bool,
     bad_response_counter: usize,
 }
 
 impl ListContext {
-    /// Create a context, starting off at the provided URL.
-    pub fn new(starting_url: Url) -> ListContext {
+    /// Create a context, starting off at the provided URL, issuing all requests through the specified session.
+    pub fn new(starting_url: Url, session: Session, config: ListConfig) -> ListContext {
         ListContext {
+            session: session,
+            config: config,
+            history: History::new(&starting_url),
             cururl: starting_url,
             files: vec![],
+            lines: vec![],
             selected: 0,
+            top: 0,
+            filter: None,
+            status: None,
+            selections: HashMap::new(),
+            reselect: None,
             have_write: false,
             bad_response_counter: 0,
         }
@@ -160,14 +317,58 @@ impl ListContext {
     /// Key|Result
     /// -------|-------
     /// Enter/Right Arrow | enter highlighted entry
-    /// Escape/`'Q'`/`'q'` | end
+    /// Escape | clear filter, if any, otherwise end
+    /// `'Q'`/`'q'`/Ctrl+C | end
     /// `'D'`/`'d'` | download file
     /// `'U'`/`'u'` | upload file
+    /// `'B'`/`'b'` | bookmark current directory
+    /// `'G'`/`'g'` | go to bookmark
+    /// `'['` | go back in history
+    /// `']'` | go forward in history
+    /// `'H'`/`'h'` | show recent locations to go to one
+    /// `'/'` | [filter](#filtering) entries
+    /// `'n'` | select next entry matching filter
+    /// `'N'` | select previous entry matching filter
     /// Up Arrow | move selection 1 entry up
     /// Down Arrow | move selection 1 entry down
+    /// Page Up | move selection 1 screen up
+    /// Page Down | move selection 1 screen down
+    /// Home | select first entry
+    /// End | select last entry
     /// Left Arrow | go up one level, if not at root
     /// Delete | `DELETE` highlighted entry
     ///
+    /// The character keys can be rebound, see [`KeyBindings`](struct.KeyBindings.html).
+    ///
+    /// ### Screen
+    ///
+    /// The screen is cleared and redrawn after every action: a header line with the current directory,
+    /// as many entries as fit in `term_size`, and a status line with the position of the selection
+    /// and the outcome of the last action, like `<Success!>`.
+    ///
+    /// The shown entries scroll to keep the selection visible, and are laid out anew when the terminal is resized.
+    ///
+    /// ### Filtering
+    ///
+    /// The user types a pattern in the status line, and as they do, only the entries with names matching it are shown,
+    /// and the first one of them is selected, see [`Filter`](filter/struct.Filter.html).
+    ///
+    /// Key|Result
+    /// ---|------
+    /// Tab | switch between substring, glob and fuzzy matching
+    /// Enter | keep the filter until the directory is left
+    /// Escape | clear the filter and restore the selection
+    ///
+    /// A kept filter matching no entries is cleared.
+    ///
+    /// ### History
+    ///
+    /// Entering entries, going up, and going to bookmarks or recent locations are recorded like in a browser,
+    /// going back or forward restores the entry selected in the location.
+    ///
+    /// When a directory is listed again, the entry last selected in it is selected,
+    /// except when going up, in which case the directory just left is selected.
+    ///
     /// ### Entering entries
     ///
     /// If the entry is a directory, on the next loop the selected subdirectory wll be listed.
@@ -179,13 +380,22 @@ impl ListContext {
     /// ### Downloading files
     ///
     /// The user is shown a file picker and let choose where to download the file to,
-    /// then the file is downloaded and saved to the specified location.
+    /// then the file is downloaded and saved to the specified location,
+    /// resuming the previous attempt if it was interrupted, see [`Session::download_resumable()`](struct.Session.html#method.download_resumable).
     ///
     /// The file isn't saved if the user cancels the picker.
     ///
+    /// ### Errors
+    ///
+    /// Failures to communicate with the server and non-success responses are shown in the status line,
+    /// after which the listing continues at the previous location in history, or else one level up;
+    /// if there's neither, the error is returned.
+    ///
+    /// If the server's response can't be parsed thrice in a row at root, the error is returned.
+    ///
     /// ### Uploading files
     ///
-    /// If the server, in the last RFSAPI request, specified `writes_supported` as `false`, an error is printed,
+    /// If the server, in the last RFSAPI request, specified `writes_supported` as `false`, an error is shown,
     /// and nothing happens.
     ///
     /// Otherwise:
@@ -195,45 +405,94 @@ impl ListContext {
     /// with the name of the picked file.
     ///
     /// The file isn't uploaded if the user cancels the picker.
-    pub fn one_loop<W: Write>(&mut self, mut out: &mut W, input: &Getch, term_size: (usize, usize)) -> io::Result<bool> {
-        try!(writeln!(out, "Contents of {}:", percent_decode(&self.cururl.to_string()).unwrap()));
-        let mut resp = download(self.cururl.clone());
+    ///
+    /// ### Bookmarks
+    ///
+    /// When bookmarking, the user is asked for the bookmark's name, defaulting to the directory's name.
+    ///
+    /// When going to a bookmark or a recent location, they're all listed,
+    /// and the user is asked for the number or name of the one to go to.
+    ///
+    /// Pressing Escape at either prompt cancels.
+    pub fn one_loop<W: Write>(&mut self, out: &mut W, input: &Input, term_size: &TermSize) -> Result<bool, Error> {
+        self.files.clear();
+        let mut resp = match self.session.download(self.cururl.clone()) {
+            Ok(r) => r,
+            Err(e) => {
+                self.status = Some(format!("<{}...>", e));
+                return if self.leave_failed() { Ok(true) } else { Err(e) };
+            }
+        };
         if !resp.status().is_success() {
-            try!(writeln!(out, "<Got {}...>", resp.status()));
-            self.back();
-            return Ok(true);
+            self.status = Some(format!("<Got {}...>", resp.status()));
+            // Refetching the same location would just fail again, with nothing drawn
+            return if self.leave_failed() {
+                Ok(true)
+            } else {
+                Err(Error::HttpStatus(self.cururl.clone(), resp.status()))
+            };
         }
 
         let data = match resp.json::<FilesetData>() {
             Ok(d) => d,
             Err(e) => {
-                try!(writeln!(out, "<Couldn't parse server response: {}...>", e));
                 self.bad_response_counter += !self.back() as usize;
                 if self.bad_response_counter == 3 {
-                    try!(writeln!(out, "<Server at {} doesn't support RFSAPI.>", self.cururl));
+                    return Err(Error::RfsapiParse(self.cururl.clone(), e));
                 }
-                return Ok(self.bad_response_counter != 3);
+                self.status = Some(format!("<Couldn't parse server response: {}...>", e));
+                return Ok(true);
             }
         };
         self.have_write = data.writes_supported;
 
         if data.is_file {
-            if !try!(paging_copy(&mut download_raw(self.cururl.clone()), out, &self.cururl.path()[1..], input, term_size)) {
-                try!(writeln!(out, "<Not UTF-8, select download destination>"));
-                try!(self.download_file(out, self.cururl.clone()));
+            try!(out.flush());
+            try!(write!(out, "{}", term::clear_screen()));
+            match self.session.download_raw(self.cururl.clone()) {
+                Ok(mut resp) => {
+                    if !try!(paging_copy(&mut resp, out, &self.cururl.path()[1..], input, term_size)) {
+                        try!(writeln!(out, "<Not UTF-8, select download destination>"));
+                        let u = self.cururl.clone();
+                        try!(self.download_file(out, u));
+                    }
+                }
+                Err(e) => self.status = Some(format!("<{}...>", e)),
             }
-            self.cururl = parent_url(&self.cururl);
+            self.leave_file();
         } else {
             self.files = RemoteFile::from_response(data);
+            if let Some(order) = self.config.sort {
+                RemoteFile::sort(&mut self.files, order);
+            }
+            if let Some(name) = self.reselect.take() {
+                if let Some(idx) = self.files.iter().position(|f| f.full_name == name) {
+                    self.selected = idx;
+                }
+            }
+            self.selected = cmp::min(self.selected, self.files.len().saturating_sub(1));
+            if self.filter.is_some() {
+                let shown = self.shown();
+                if shown.is_empty() {
+                    self.filter = None;
+                } else if !shown.contains(&self.selected) {
+                    self.selected = shown[0];
+                }
+            }
             {
-                let mut tout = TabWriter::new(&mut out);
-                for (i, f) in self.files.iter().enumerate() {
-                    try!(writeln!(tout, "{}{}", if i == self.selected { ">" } else { " " }, f));
+                let mut buf = vec![];
+                {
+                    let mut tout = TabWriter::new(&mut buf);
+                    for f in &self.files {
+                        try!(writeln!(tout, "{}", f));
+                    }
+                    try!(tout.flush());
                 }
-                try!(tout.flush());
+                self.lines = String::from_utf8_lossy(&buf).lines().map(str::to_string).collect();
             }
+            try!(self.draw(out, term_size));
 
-            while let (true, exit) = try!(self.process_input(out, input)) {
+            while let (true, exit) = try!(self.process_input(out, input, term_size)) {
                 if exit {
                     return Ok(false);
                 }
@@ -243,71 +502,223 @@ impl ListContext {
         Ok(true)
     }
 
-    fn process_input<W: Write>(&mut self, out: &mut W, input: &Getch) -> io::Result<(bool, bool)> {
-        match try!(input.getch()) {
-            GETCH_ENTER => Ok((self.select(), false)),
-            GETCH_ESC | b'q' | b'Q' => Ok((true, true)),
-            b'd' | b'D' => {
+    fn process_input<W: Write>(&mut self, out: &mut W, input: &Input, term_size: &TermSize) -> io::Result<(bool, bool)> {
+        let press = try!(input.read_key());
+        let shown = self.shown();
+        let pos = shown.iter().position(|&i| i == self.selected).unwrap_or(0);
+        let last = shown.len().saturating_sub(1);
+        let rows = ListContext::viewport_rows(term_size.get());
+        let new_pos = match press.key {
+            Key::Up => Some(pos.saturating_sub(1)),
+            Key::Down => Some(cmp::min(pos + 1, last)),
+            Key::PageUp => Some(pos.saturating_sub(rows)),
+            Key::PageDown => Some(cmp::min(pos + rows, last)),
+            Key::Home => Some(0),
+            Key::End => Some(last),
+            _ if self.filter.is_some() && press.is_exact_char(self.config.keys.next_match) => Some(if pos == last { 0 } else { pos + 1 }),
+            _ if self.filter.is_some() && press.is_exact_char(self.config.keys.previous_match) => Some(if pos == 0 { last } else { pos - 1 }),
+            _ => None,
+        };
+        if let Some(new_pos) = new_pos {
+            if let Some(&new_selected) = shown.get(new_pos) {
+                if new_selected != self.selected {
+                    self.selected = new_selected;
+                    try!(self.draw(out, term_size));
+                }
+            }
+            return Ok((true, false));
+        }
+
+        if press.key == Key::Resize {
+            try!(self.draw(out, term_size));
+            return Ok((true, false));
+        }
+
+        self.status = None;
+        match press.key {
+            Key::Enter | Key::Right => Ok((self.select(), false)),
+            Key::Escape if self.filter.is_some() => {
+                self.filter = None;
+                try!(self.draw(out, term_size));
+                Ok((true, false))
+            }
+            Key::Escape => Ok((true, true)),
+            _ if press.is_interrupt() => Ok((true, true)),
+            Key::Left => Ok((self.back(), false)),
+            Key::Delete => {
+                if self.have_write {
+                    try!(self.delete(out));
+                } else {
+                    self.status = Some("<Server doesn't permit write requests>".to_string());
+                }
+                Ok((false, false))
+            }
+            _ if press.is_char(self.config.keys.quit) => Ok((true, true)),
+            _ if press.is_char(self.config.keys.filter) => {
+                try!(self.read_filter(out, input, term_size));
+                Ok((true, false))
+            }
+            _ if press.is_char(self.config.keys.download) => {
                 let download_ok = !self.files.is_empty() && self.files[self.selected].size.is_some();
                 if download_ok {
-                    try!(self.download_file(out, self.cururl.join(&self.files[self.selected].full_name).unwrap()));
+                    let u = self.cururl.join(&self.files[self.selected].full_name).unwrap();
+                    try!(self.download_file(out, u));
                 }
                 Ok((!download_ok, false))
             }
-            b'u' | b'U' => {
+            _ if press.is_char(self.config.keys.bookmark) => {
+                try!(self.add_bookmark(out, input));
+                Ok((false, false))
+            }
+            _ if press.is_char(self.config.keys.bookmarks) => {
+                try!(self.go_to_bookmark(out, input));
+                Ok((false, false))
+            }
+            _ if press.is_char(self.config.keys.back) => {
+                let current = self.visit();
+                Ok((!self.restore(|h| h.back(current)), false))
+            }
+            _ if press.is_char(self.config.keys.forward) => {
+                let current = self.visit();
+                Ok((!self.restore(|h| h.forward(current)), false))
+            }
+            _ if press.is_char(self.config.keys.history) => {
+                let recent: Vec<_> = self.history
+                    .recent()
+                    .map(|u| (url_file_name(u).or_else(|| u.host_str().map(str::to_string)).unwrap_or_default(), u.clone()))
+                    .collect();
+                if let Some(u) = try!(self.pick_url(out, input, "Go to location", &recent)) {
+                    self.go_to(u);
+                }
+                Ok((false, false))
+            }
+            _ if press.is_char(self.config.keys.upload) => {
                 if self.have_write {
                     try!(self.upload(out));
                 } else {
-                    try!(writeln!(out, "<Server doesn't permit write requests>"));
+                    self.status = Some("<Server doesn't permit write requests>".to_string());
                 }
                 Ok((false, false))
             }
-            GETCH_SPECIAL_PREFIX => {
-                match try!(input.getch()) {
-                    GETCH_ARROW_UP => {
-                        if self.selected != 0 {
-                            try!(self.update_selected(out, ' '));
-                            self.selected -= 1;
-                            try!(self.update_selected(out, '>'));
-                        }
-                        Ok((true, false))
-                    }
-                    GETCH_ARROW_DOWN => {
-                        try!(self.update_selected(out, ' '));
-                        self.selected = cmp::min(self.selected + 1, self.files.len() - 1);
-                        try!(self.update_selected(out, '>'));
-                        Ok((true, false))
-                    }
-                    GETCH_ARROW_LEFT => Ok((self.back(), false)),
-                    GETCH_ARROW_RIGHT => Ok((self.select(), false)),
-                    GETCH_DELETE => {
-                        if self.have_write {
-                            try!(self.delete(out));
-                        } else {
-                            try!(writeln!(out, "<Server doesn't permit write requests>"));
-                        }
-                        Ok((false, false))
-                    }
-                    _ => Ok((true, false)),
-                }
-            }
             _ => Ok((true, false)),
         }
     }
 
-    fn update_selected<W: Write>(&self, out: &mut W, c: char) -> io::Result<()> {
-        try!(write!(out, "{}", term::move_cursor_up(self.files.len() - self.selected)));
-        try!(write!(out, "{}", c));
+    /// Get the amount of entries shown at once, leaving space for the header, status and cursor lines.
+    fn viewport_rows(size: (usize, usize)) -> usize {
+        cmp::max(size.1.saturating_sub(3), 1)
+    }
+
+    /// Get the indices of the entries matching the filter, or of all entries if there's none.
+    fn shown(&self) -> Vec<usize> {
+        match self.filter {
+            Some(ref filter) => (0..self.files.len()).filter(|&i| filter.matches(&self.files[i].full_name)).collect(),
+            None => (0..self.files.len()).collect(),
+        }
+    }
+
+    /// Clear the screen and draw the current directory, the shown entries around the selection,
+    /// and the selection's position along with the status.
+    fn draw<W: Write>(&mut self, out: &mut W, term_size: &TermSize) -> io::Result<()> {
+        let size = term_size.get();
+        let rows = ListContext::viewport_rows(size);
+        let width = size.0.saturating_sub(1);
+        let shown = self.shown();
+
+        let pos = shown.iter().position(|&i| i == self.selected).unwrap_or(0);
+        if pos < self.top {
+            self.top = pos;
+        } else if pos >= self.top + rows {
+            self.top = pos + 1 - rows;
+        }
+        self.top = cmp::min(self.top, shown.len().saturating_sub(rows));
+
         try!(out.flush());
-        try!(write!(out, "{}", term::move_cursor_back(1)));
-        try!(write!(out, "{}", term::move_cursor_down(self.files.len() - self.selected)));
+        try!(write!(out, "{}", term::clear_screen()));
+
+        let header = format!("Contents of {}:", percent_decode(&self.cururl.to_string()).unwrap());
+        try!(writeln!(out, "{}", header.chars().take(width).collect::<String>()));
+        for &i in shown.iter().skip(self.top).take(rows) {
+            try!(writeln!(out,
+                          "{}{}",
+                          if i == self.selected { ">" } else { " " },
+                          self.lines.get(i).map_or("", |l| &l[..]).chars().take(width.saturating_sub(1)).collect::<String>()));
+        }
+
+        let mut status = if self.files.is_empty() {
+            "<Empty>".to_string()
+        } else if shown.is_empty() {
+            "<No matches>".to_string()
+        } else {
+            format!("<{}/{}, showing {}-{}>", pos + 1, shown.len(), self.top + 1, cmp::min(self.top + rows, shown.len()))
+        };
+        if let Some(ref filter) = self.filter {
+            status.push_str(&format!(" <Filter ({}): {}>", filter.mode, filter.pattern));
+        }
+        if let Some(ref msg) = self.status {
+            status.push(' ');
+            status.push_str(msg);
+        }
+        try!(writeln!(out, "{}", status.chars().take(width).collect::<String>()));
+        out.flush()
+    }
+
+    /// Let the user type a filter, narrowing the shown entries down live, see [Filtering](#filtering).
+    fn read_filter<W: Write>(&mut self, out: &mut W, input: &Input, term_size: &TermSize) -> io::Result<()> {
+        let previous = (self.filter.take(), self.selected);
+        let mut filter = Filter {
+            pattern: String::new(),
+            mode: previous.0.as_ref().map(|f| f.mode).unwrap_or_default(),
+        };
+
+        loop {
+            self.filter = Some(filter.clone());
+            self.status = Some("<Type to filter, Tab to switch matching, Enter to keep, Escape to clear>".to_string());
+            try!(self.draw(out, term_size));
+
+            let press = try!(input.read_key());
+            match press.key {
+                Key::Enter => break,
+                _ if press.key == Key::Escape || press.is_interrupt() => {
+                    self.filter = None;
+                    self.selected = previous.1;
+                    self.status = None;
+                    return Ok(());
+                }
+                Key::Tab => filter.mode = filter.mode.next(),
+                Key::Backspace => {
+                    filter.pattern.pop();
+                }
+                Key::Char(c) if !press.ctrl && !press.alt => filter.pattern.push(c),
+                _ => continue,
+            }
+
+            let first = self.files.iter().position(|f| filter.matches(&f.full_name));
+            if let Some(first) = first {
+                self.selected = first;
+            }
+        }
+
+        self.status = None;
+        if filter.pattern.is_empty() {
+            self.filter = None;
+        } else if self.shown().is_empty() {
+            self.filter = None;
+            self.selected = previous.1;
+            self.status = Some(format!("<No matches for {}>", filter.pattern));
+        }
+        try!(self.draw(out, term_size));
         Ok(())
     }
 
     fn select(&mut self) -> bool {
         if !self.files.is_empty() {
-            self.cururl = self.cururl.join(&self.files[self.selected].full_name).unwrap();
-            self.selected = 0;
+            if self.files[self.selected].full_name == "../" {
+                self.back();
+            } else {
+                let u = self.cururl.join(&self.files[self.selected].full_name).unwrap();
+                self.go_to(u);
+            }
         }
         self.files.is_empty()
     }
@@ -315,34 +726,167 @@ impl ListContext {
     fn back(&mut self) -> bool {
         let parent = parent_url(&self.cururl);
         if parent != self.cururl {
-            self.cururl = parent;
-            self.selected = 0;
+            let child = url_file_name(&self.cururl).map(|n| n + "/");
+            self.go_to(parent);
+            self.reselect = child.or(self.reselect.take());
             true
         } else {
             false
         }
     }
 
-    fn download_file<W: Write>(&self, out: &mut W, u: Url) -> io::Result<()> {
+    /// Leave a location that couldn't be fetched, returning whether there was anywhere to go.
+    ///
+    /// The whole server may be unreachable, so this returns to where the user came from rather than the parent, if it can.
+    fn leave_failed(&mut self) -> bool {
+        let current = self.visit();
+        self.restore(|h| h.back(current)) || self.back()
+    }
+
+    /// Go to the parent of the paged file, back in history if that's where it was entered from.
+    fn leave_file(&mut self) {
+        let parent = parent_url(&self.cururl);
+        if self.history.peek_back().map(|v| v.url == parent).unwrap_or(false) {
+            let current = self.visit();
+            self.restore(|h| h.back(current));
+        } else {
+            self.reselect = self.selections.get(&parent).cloned();
+            self.cururl = parent;
+            self.selected = 0;
+            self.filter = None;
+        }
+    }
+
+    /// Navigate to the specified location, recording it in history.
+    fn go_to(&mut self, u: Url) {
+        self.remember_selection();
+        let current = self.visit();
+        self.history.visit(current, &u);
+        self.reselect = self.selections.get(&u).cloned();
+        self.cururl = u;
+        self.selected = 0;
+        self.filter = None;
+    }
+
+    /// Go to the location returned by `f`, if any, restoring its selection, returning whether it did.
+    fn restore<F: FnOnce(&mut History) -> Option<Visit>>(&mut self, f: F) -> bool {
+        self.remember_selection();
+        match f(&mut self.history) {
+            Some(v) => {
+                self.reselect = self.selections.get(&v.url).cloned();
+                self.cururl = v.url;
+                self.selected = v.selected;
+                self.filter = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remember the name of the selected entry in the current listing, if any.
+    fn remember_selection(&mut self) {
+        if let Some(f) = self.files.get(self.selected) {
+            self.selections.insert(self.cururl.clone(), f.full_name.clone());
+        }
+    }
+
+    fn visit(&self) -> Visit {
+        Visit {
+            url: self.cururl.clone(),
+            selected: self.selected,
+        }
+    }
+
+    fn add_bookmark<W: Write>(&mut self, out: &mut W, input: &Input) -> io::Result<()> {
+        let path = match self.config.bookmarks.clone() {
+            Some(p) => p,
+            None => {
+                self.status = Some("<Bookmarks unavailable>".to_string());
+                return Ok(());
+            }
+        };
+
+        let default_name = url_file_name(&self.cururl).or_else(|| self.cururl.host_str().map(str::to_string)).unwrap_or_default();
+        try!(write!(out, "<Bookmark name [{}]> ", default_name));
+        try!(out.flush());
+        let name = match try!(input.read_line(true)) {
+            Some(ref n) if n.trim().is_empty() => default_name,
+            Some(n) => n.trim().to_string(),
+            None => return Ok(()),
+        };
+
+        let cururl = self.cururl.clone();
+        self.status = Some(match Bookmarks::load(&path).and_then(|mut bookmarks| {
+            bookmarks.insert(name.clone(), cururl);
+            bookmarks.save(&path)
+        }) {
+            Ok(()) => format!("<Bookmarked as {}>", name),
+            Err(e) => format!("<Couldn't save bookmark: {}...>", e),
+        });
+        Ok(())
+    }
+
+    fn go_to_bookmark<W: Write>(&mut self, out: &mut W, input: &Input) -> io::Result<()> {
+        let bookmarks = match self.config.bookmarks.as_ref().map(|p| Bookmarks::load(p)) {
+            Some(Ok(b)) => b,
+            Some(Err(e)) => {
+                self.status = Some(format!("<Couldn't load bookmarks: {}...>", e));
+                return Ok(());
+            }
+            None => {
+                self.status = Some("<Bookmarks unavailable>".to_string());
+                return Ok(());
+            }
+        };
+        if bookmarks.is_empty() {
+            self.status = Some("<No bookmarks>".to_string());
+            return Ok(());
+        }
+
+        let bookmarks: Vec<_> = bookmarks.iter().map(|(name, u)| (name.clone(), u.clone())).collect();
+        if let Some(u) = try!(self.pick_url(out, input, "Go to bookmark", &bookmarks)) {
+            self.go_to(u);
+        }
+        Ok(())
+    }
+
+    fn download_file<W: Write>(&mut self, out: &mut W, u: Url) -> io::Result<()> {
         let f = PathBuf::from(&u.path()[1..]);
-        if let Some(outp) = term::save_file_picker(f.file_name().unwrap(), f.extension()) {
+        let fname = match self.config.download_dir {
+            Some(ref dir) => dir.join(f.file_name().unwrap()),
+            None => PathBuf::from(f.file_name().unwrap()),
+        };
+        if let Some(outp) = term::save_file_picker(fname.as_os_str(), f.extension()) {
             try!(writeln!(out, "<Downloading to {}...>", outp.display()));
-            try!(io::copy(&mut download_raw(u), &mut try!(File::create(&outp))));
-            try!(writeln!(out, "<Done!>"));
+            self.status = Some(match self.session.download_resumable(u, &outp) {
+                Ok(status) => {
+                    if status.is_success() {
+                        "<Done!>".to_string()
+                    } else {
+                        format!("<Got {}...>", status)
+                    }
+                }
+                Err(e) => format!("<{}...>", e),
+            });
         }
         Ok(())
     }
 
-    fn upload<W: Write>(&self, out: &mut W) -> io::Result<()> {
+    fn upload<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
         if let Some(inp) = term::open_file_picker() {
             try!(writeln!(out, "<Uploading {} to {}...>", inp.display(), percent_decode(&self.cururl.to_string()).unwrap()));
             let upurl = self.cururl.join(&Path::new(inp.file_name().unwrap()).display().to_string()).unwrap();
-            let status = upload(upurl, try!(File::open(inp))).status();
-            if status.is_success() {
-                try!(writeln!(out, "<Success!>"));
-            } else {
-                try!(writeln!(out, "<Got {}...>", status));
-            }
+            let result = File::open(inp).map_err(Error::from).and_then(|f| self.session.upload(upurl, f));
+            self.status = Some(match result {
+                Ok(resp) => {
+                    if resp.status().is_success() {
+                        "<Success!>".to_string()
+                    } else {
+                        format!("<Got {}...>", resp.status())
+                    }
+                }
+                Err(e) => format!("<{}...>", e),
+            });
         }
         Ok(())
     }
@@ -350,15 +894,50 @@ impl ListContext {
     fn delete<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
         let delurl = self.cururl.join(&self.files[self.selected].full_name).unwrap();
         try!(writeln!(out, "<Deleting {}...>", percent_decode(&delurl.to_string()).unwrap()));
-        let status = delete(delurl.clone()).status();
-        if status.is_success() {
-            try!(writeln!(out, "<Success!>"));
-        } else {
-            try!(writeln!(out, "<Got {}...>", status));
-        }
-        self.selected = 0;
+        self.status = Some(match self.session.delete(delurl) {
+            Ok(resp) => {
+                if resp.status().is_success() {
+                    "<Success!>".to_string()
+                } else {
+                    format!("<Got {}...>", resp.status())
+                }
+            }
+            Err(e) => format!("<{}...>", e),
+        });
         Ok(())
     }
+
+
+    /// List the named `choices`, numbered, and ask the user to pick one by number or name.
+    ///
+    /// Returns `Ok(None)` if the user cancelled or picked a nonexistant one, which is reported in the status.
+    fn pick_url<W: Write>(&mut self, out: &mut W, input: &Input, prompt: &str, choices: &[(String, Url)]) -> io::Result<Option<Url>> {
+        {
+            let mut tout = TabWriter::new(&mut *out);
+            for (i, &(ref name, ref u)) in choices.iter().enumerate() {
+                try!(writeln!(tout, "{}\t{}\t{}", i + 1, name, percent_decode(u.as_str()).unwrap_or_else(|| u.as_str().into())));
+            }
+            try!(tout.flush());
+        }
+        try!(write!(out, "<{}> ", prompt));
+        try!(out.flush());
+        let choice = match try!(input.read_line(true)) {
+            Some(c) => c.trim().to_string(),
+            None => return Ok(None),
+        };
+
+        let picked = match choice.parse::<usize>() {
+            Ok(i) if i != 0 && i <= choices.len() => Some(&choices[i - 1]),
+            _ => choices.iter().find(|&&(ref name, _)| *name == choice),
+        };
+        match picked {
+            Some(&(_, ref u)) => Ok(Some(u.clone())),
+            None => {
+                self.status = Some(format!("<No {}>", choice));
+                Ok(None)
+            }
+        }
+    }
 }
 
 
@@ -458,6 +1037,40 @@ impl RemoteFile {
             fs
         }
     }
+
+    /// Sort a listing returned by [`from_response()`](#method.from_response) in the specified order,
+    /// keeping the `"../"` entry and directories first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use doh::ops::{RemoteFile, SortOrder};
+    /// let file = |name: &str, size| RemoteFile {
+    ///     full_name: name.to_string(),
+    ///     size: size,
+    ///     human_size: None,
+    ///     last_modified: None,
+    /// };
+    /// let mut files = vec![file("../", None), file("src/", None), file("b.txt", Some(10)), file("A.txt", Some(5))];
+    ///
+    /// RemoteFile::sort(&mut files, SortOrder::Name);
+    /// assert_eq!(files.iter().map(|f| &f.full_name[..]).collect::<Vec<_>>(), vec!["../", "src/", "A.txt", "b.txt"]);
+    ///
+    /// RemoteFile::sort(&mut files, SortOrder::Size);
+    /// assert_eq!(files.iter().map(|f| &f.full_name[..]).collect::<Vec<_>>(), vec!["../", "src/", "b.txt", "A.txt"]);
+    /// ```
+    pub fn sort(files: &mut [RemoteFile], order: SortOrder) {
+        files.sort_by(|l, r| {
+            (l.full_name != "../")
+                .cmp(&(r.full_name != "../"))
+                .then_with(|| l.size.is_some().cmp(&r.size.is_some()))
+                .then_with(|| match order {
+                    SortOrder::Name => l.full_name.to_lowercase().cmp(&r.full_name.to_lowercase()),
+                    SortOrder::Size => r.size.cmp(&l.size),
+                    SortOrder::Modified => r.last_modified.cmp(&l.last_modified),
+                })
+        });
+    }
 }
 
 impl From<RawFileData> for RemoteFile {
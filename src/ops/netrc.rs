@@ -0,0 +1,195 @@
+//! Stored credential lookup, from `~/.netrc` and doh's own credentials file.
+//!
+//! Both files use the netrc format: whitespace-separated `machine HOST`, `login USER`, `password PASSWORD` tokens,
+//! with `default` matching all hosts not listed before it, and `macdef` definitions skipped.
+//! Tokens containing whitespace can be double-quoted, within which `\` escapes the next character.
+//! doh's credentials file additionally understands `token TOKEN` for HTTP Bearer authentication.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use doh::ops::netrc;
+//! if let Some(creds) = netrc::lookup("example.com") {
+//!     println!("Found {:?}", creds);
+//! }
+//! ```
+
+
+use self::super::super::util::{config_dir, home_dir};
+use std::io::{self, Write, Read, stderr};
+use self::super::Credentials;
+use std::path::{PathBuf, Path};
+use std::fs::File;
+
+
+/// Get the path to the current user's netrc file, if the home directory is known.
+pub fn netrc_path() -> Option<PathBuf> {
+    home_dir().map(|h| h.join(if cfg!(target_os = "windows") {
+        "_netrc"
+    } else {
+        ".netrc"
+    }))
+}
+
+/// Get the path to doh's credentials file, if the config directory is known.
+pub fn credentials_path() -> Option<PathBuf> {
+    config_dir().map(|c| c.join("credentials"))
+}
+
+/// Find credentials for `host` in doh's credentials file, then in the netrc file.
+///
+/// Files that don't exist are skipped silently,
+/// unreadable ones and ones accessible by other users are skipped with a warning on stderr.
+pub fn lookup(host: &str) -> Option<Credentials> {
+    credentials_path().into_iter().chain(netrc_path()).filter_map(|p| match read_private(&p) {
+        Ok(Some(content)) => parse(&content, host),
+        Ok(None) => None,
+        Err(e) => {
+            let _ = writeln!(stderr(), "Ignoring {}: {}.", p.display(), e);
+            None
+        }
+    }).next()
+}
+
+/// Find credentials for `host` in the specified netrc-format data.
+///
+/// Basic credentials are returned for entries with a `login`, Bearer ones for entries with a `token` and no `login`.
+///
+/// # Examples
+///
+/// ```
+/// # use doh::ops::netrc::parse;
+/// # use doh::ops::Credentials;
+/// let netrc = "machine example.com login user password hunter2
+///              machine api.example.com token abcdef
+///              default login anonymous";
+/// assert_eq!(parse(netrc, "example.com"),
+///            Some(Credentials::Basic {
+///                username: "user".to_string(),
+///                password: Some("hunter2".to_string()),
+///            }));
+/// assert_eq!(parse(netrc, "api.example.com"), Some(Credentials::Bearer("abcdef".to_string())));
+/// assert_eq!(parse(netrc, "example.org"),
+///            Some(Credentials::Basic {
+///                username: "anonymous".to_string(),
+///                password: None,
+///            }));
+/// assert_eq!(parse("machine example.com login user", "example.org"), None);
+/// ```
+pub fn parse(data: &str, host: &str) -> Option<Credentials> {
+    let mut tokens = vec![];
+    let mut lines = data.lines();
+    while let Some(line) = lines.next() {
+        for tok in split_tokens(line) {
+            if tok == "macdef" {
+                // Macro definitions last until an empty line
+                while lines.next().map(|l| !l.trim().is_empty()).unwrap_or(false) {}
+                break;
+            }
+            tokens.push(tok);
+        }
+    }
+
+    let mut tokens = tokens.into_iter();
+    let mut matching = false;
+    let (mut login, mut password, mut token) = (None, None, None);
+    while let Some(tok) = tokens.next() {
+        match &tok[..] {
+            "machine" | "default" if matching => break,
+            "machine" => matching = tokens.next().map(|m| m.eq_ignore_ascii_case(host)).unwrap_or(false),
+            "default" => matching = true,
+            "login" | "password" | "token" | "account" => {
+                let value = tokens.next();
+                if matching {
+                    match &tok[..] {
+                        "login" => login = value,
+                        "password" => password = value,
+                        "token" => token = value,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    match (login, token) {
+        (Some(username), _) => {
+            Some(Credentials::Basic {
+                username: username,
+                password: password,
+            })
+        }
+        (None, Some(token)) => Some(Credentials::Bearer(token)),
+        (None, None) => None,
+    }
+}
+
+/// Split a line into whitespace-separated tokens, which may be double-quoted, with `\` escaping the next character in quotes.
+fn split_tokens(line: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.peek().map(|c| c.is_whitespace()).unwrap_or(false) {
+            chars.next();
+        }
+        let mut tok = String::new();
+        match chars.next() {
+            Some('"') => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => tok.extend(chars.next()),
+                        c => tok.push(c),
+                    }
+                }
+            }
+            Some(c) => {
+                tok.push(c);
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    tok.push(c);
+                    chars.next();
+                }
+            }
+            None => return tokens,
+        }
+        tokens.push(tok);
+    }
+}
+
+/// Read the file at `p`, if it exists and isn't accessible by other users.
+fn read_private(p: &Path) -> io::Result<Option<String>> {
+    let mut f = match File::open(p) {
+        Ok(f) => f,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    try!(check_permissions(&f));
+
+    let mut content = String::new();
+    try!(f.read_to_string(&mut content));
+    Ok(Some(content))
+}
+
+/// Check that the file isn't accessible by other users, always passes on Windows.
+#[cfg(not(target_os = "windows"))]
+pub fn check_permissions(f: &File) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = try!(f.metadata()).permissions().mode();
+    if mode & 0o077 != 0 {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied,
+                           format!("accessible by other users (mode {:03o}), restrict it with chmod 600", mode & 0o777)))
+    } else {
+        Ok(())
+    }
+}
+
+/// Check that the file isn't accessible by other users, always passes on Windows.
+#[cfg(target_os = "windows")]
+pub fn check_permissions(_: &File) -> io::Result<()> {
+    Ok(())
+}
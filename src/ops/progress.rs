@@ -0,0 +1,149 @@
+//! Transfer progress display.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use doh::ops::progress::ProgressReader;
+//! # use std::fs::File;
+//! # use std::io;
+//! let f = File::open("file.bin").unwrap();
+//! let len = f.metadata().unwrap().len();
+//! io::copy(&mut ProgressReader::new(f, "file.bin", 0, Some(len)), &mut io::sink()).unwrap();
+//! ```
+
+
+use self::super::super::util::{human_readable_duration, human_readable_size};
+use std::io::{self, Write, Read, stdout};
+use std::time::{Duration, Instant};
+use atty::{self, Stream};
+use term_size;
+use std::{cmp, iter};
+use super::term;
+
+
+/// A reader wrapper, keeping a progress line with the transfer's percentage, size, rate and ETA on stdout
+/// up to date while it's being read.
+///
+/// The line is redrawn in-place at most every 100ms, cut to fit the terminal, and cleared when the reader is dropped.
+///
+/// Nothing is displayed if stdout isn't a terminal.
+pub struct ProgressReader<R: Read> {
+    inner: R,
+    label: String,
+    initial: u64,
+    done: u64,
+    total: Option<u64>,
+    start: Instant,
+    last_draw: Option<Instant>,
+    drawn_len: usize,
+    enabled: bool,
+}
+
+impl<R: Read> ProgressReader<R> {
+    /// Wrap the specified reader, labelling the transfer with `label`.
+    ///
+    /// `done` is the amount of bytes transferred before `inner` started being read, e.g. when resuming,
+    /// and `total` is the total size of the transfer (including `done`), if known.
+    pub fn new<L: Into<String>>(inner: R, label: L, done: u64, total: Option<u64>) -> ProgressReader<R> {
+        ProgressReader {
+            inner: inner,
+            label: label.into(),
+            initial: done,
+            done: done,
+            total: total,
+            start: Instant::now(),
+            last_draw: None,
+            drawn_len: 0,
+            enabled: atty::is(Stream::Stdout),
+        }
+    }
+
+    fn draw(&mut self, force: bool) -> io::Result<()> {
+        let now = Instant::now();
+        if !self.enabled || (!force && self.last_draw.map(|ld| now.duration_since(ld) < Duration::from_millis(100)).unwrap_or(false)) {
+            return Ok(());
+        }
+        self.last_draw = Some(now);
+
+        let elapsed = now.duration_since(self.start);
+        let elapsed = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1_000_000_000f64;
+        let rate = if elapsed > 0f64 {
+            (self.done - self.initial) as f64 / elapsed
+        } else {
+            0f64
+        };
+
+        let mut line = format!("<{}> ", self.label);
+        if let Some(total) = self.total {
+            line.push_str(&format!("<{}%> <{}/{}> ",
+                                   if total == 0 {
+                                       100
+                                   } else {
+                                       self.done * 100 / total
+                                   },
+                                   human_readable_size(self.done),
+                                   human_readable_size(total)));
+        } else {
+            line.push_str(&format!("<{}> ", human_readable_size(self.done)));
+        }
+        line.push_str(&format!("<{}/s>", human_readable_size(rate as u64)));
+        if let (Some(total), true) = (self.total, rate >= 1f64) {
+            line.push_str(&format!(" <ETA {}>", human_readable_duration((total.saturating_sub(self.done) as f64 / rate) as u64)));
+        }
+
+        // Leave the last column free, so that the line never wraps, which would break rewinding
+        let width = term_size::dimensions().map(|(w, _)| w.saturating_sub(1)).unwrap_or(usize::max_value());
+        if line.chars().count() > width {
+            line = line.chars().take(width).collect();
+        }
+
+        let len = line.chars().count();
+        let padding = cmp::min(self.drawn_len, width).saturating_sub(len);
+        try!(self.rewind());
+        let stdout = stdout();
+        let mut out = stdout.lock();
+        try!(write!(out, "{}{}", line, iter::repeat(' ').take(padding).collect::<String>()));
+        try!(out.flush());
+        self.drawn_len = len + padding;
+        Ok(())
+    }
+
+    /// Move the cursor back to the start of the progress line.
+    fn rewind(&mut self) -> io::Result<()> {
+        if self.drawn_len != 0 {
+            let mut out = stdout();
+            try!(out.flush());
+            try!(write!(out, "{}", term::move_cursor_back(self.drawn_len)));
+            try!(out.flush());
+        }
+        Ok(())
+    }
+
+    /// Remove the progress line, leaving the cursor where it started.
+    fn clear(&mut self) -> io::Result<()> {
+        if self.drawn_len != 0 {
+            try!(self.rewind());
+            let mut out = stdout();
+            try!(write!(out, "{}", iter::repeat(' ').take(self.drawn_len).collect::<String>()));
+            try!(self.rewind());
+            self.drawn_len = 0;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = try!(self.inner.read(buf));
+        self.done += read as u64;
+        let _ = self.draw(read == 0);
+        Ok(read)
+    }
+}
+
+impl<R: Read> Drop for ProgressReader<R> {
+    /// Clear the progress line.
+    fn drop(&mut self) {
+        let _ = self.clear();
+    }
+}
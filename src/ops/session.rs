@@ -0,0 +1,454 @@
+//! HTTP client state shared by all requests.
+
+
+use reqwest::header::{ContentRangeSpec, ByteRangeSpec, ContentLength, Authorization, LastModified, ContentRange, Connection, UserAgent, HttpDate,
+                      Headers, IfRange, Bearer, Range, Basic};
+use reqwest::{self, RedirectPolicy, ClientBuilder, RequestBuilder, Certificate, StatusCode, Identity, Response, Client, Body, Url};
+use self::super::super::util::{parse_retry_after, retry_backoff, url_file_name, MAX_RETRY_DELAY, USER_AGENT};
+use std::io::{self, Write, Read, Seek, SeekFrom, stderr};
+use std::fs::{self, OpenOptions, File};
+use self::super::progress::ProgressReader;
+use self::super::input::Input;
+use self::super::proxy::ProxyConfig;
+use self::super::netrc;
+use std::path::{PathBuf, Path};
+use self::super::super::Error;
+use rfsapi::RawFsApiHeader;
+use url::Origin;
+use filetime::{self, FileTime};
+use atty::{self, Stream};
+use std::collections::HashMap;
+use std::cell::{RefCell, Cell};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{cmp, thread};
+use std::rc::Rc;
+
+
+/// Configuration of a [`Session`](struct.Session.html)'s HTTP client.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct SessionConfig {
+    /// Time connecting and each read or write of a request may take.
+    ///
+    /// reqwest 0.8 has no separate connect timeout, so a single one covers both.
+    pub timeout: Duration,
+    /// Maximal amount of times to retry requests that failed transiently.
+    pub retries: usize,
+    /// Delay before the first retry, doubled for each next one.
+    pub retry_backoff: Duration,
+    /// Maximal amount of redirects to follow, or `0` to not follow them.
+    pub max_redirects: usize,
+    /// Whether to keep connections open for reuse by later requests.
+    pub keep_alive: bool,
+    /// Credentials to authenticate requests with, each only sent to its origin (scheme, host and port).
+    pub credentials: Vec<(Origin, Credentials)>,
+    /// PEM file with an additional certificate authority to trust.
+    pub ca_bundle: Option<PathBuf>,
+    /// PKCS#12 archive with the client certificate and private key to present to servers, and its password.
+    pub client_cert: Option<(PathBuf, String)>,
+    /// Whether to skip verifying that servers' certificates are for their hostnames.
+    ///
+    /// The certificate chain is still verified, reqwest 0.8 has no way to disable that.
+    pub insecure: bool,
+    /// Proxies to route requests through.
+    pub proxy: ProxyConfig,
+}
+
+impl Default for SessionConfig {
+    fn default() -> SessionConfig {
+        SessionConfig {
+            timeout: Duration::from_secs(30),
+            retries: 3,
+            retry_backoff: Duration::from_secs(1),
+            max_redirects: 10,
+            keep_alive: true,
+            credentials: vec![],
+            ca_bundle: None,
+            client_cert: None,
+            insecure: false,
+            proxy: ProxyConfig::default(),
+        }
+    }
+}
+
+/// Credentials to authenticate requests with.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum Credentials {
+    /// HTTP Basic authentication.
+    ///
+    /// If the password isn't specified and the server demands one, it's prompted for.
+    Basic {
+        /// User to authenticate as.
+        username: String,
+        /// The user's password.
+        password: Option<String>,
+    },
+    /// HTTP Bearer token authentication.
+    Bearer(String),
+}
+
+impl Credentials {
+    fn apply(&self, req: &mut RequestBuilder) {
+        match *self {
+            Credentials::Basic { ref username, ref password } => {
+                req.header(Authorization(Basic {
+                    username: username.clone(),
+                    password: password.clone(),
+                }))
+            }
+            Credentials::Bearer(ref token) => req.header(Authorization(Bearer { token: token.clone() })),
+        };
+    }
+}
+
+
+/// An HTTP client, reused for all requests, so that connections and TLS sessions are kept between them.
+///
+/// Cloning a session is cheap, and the clones share the connection pool and credentials.
+///
+/// ### Authentication
+///
+/// Requests are authenticated with the configured credentials for their origin,
+/// or else with the ones stored for their host in the credentials file or `~/.netrc`, if any.
+///
+/// If the server responds with `401 Unauthorized` and a `WWW-Authenticate` challenge and stdin is a terminal,
+/// the user is prompted for credentials (the password or token without echo), which are then used for all later requests
+/// to the same origin;
+/// pressing Escape at the prompt gives up and returns the `401` response.
+///
+/// ### Retries
+///
+/// Requests failing with a network error (like a reset connection or a timeout) or a `502`, `503` or `504` status
+/// are retried, after a delay doubling with each attempt up to two minutes (or the initial delay, if longer),
+/// or the one specified by the response's `Retry-After` header.
+/// A message is printed to stderr before each retry.
+///
+/// Proxy credentials can't be sent along tunnelled HTTPS requests, so a warning is printed to stderr
+/// before the first such request.
+#[derive(Clone)]
+pub struct Session {
+    client: Client,
+    credentials: Rc<RefCell<HashMap<Origin, Option<Credentials>>>>,
+    proxy: ProxyConfig,
+    proxy_warned: Rc<Cell<bool>>,
+    retries: usize,
+    retry_backoff: Duration,
+}
+
+impl Session {
+    /// Create a session with a client configured as specified.
+    pub fn new(config: &SessionConfig) -> Result<Session, Error> {
+        let mut headers = Headers::new();
+        headers.set(UserAgent::new(USER_AGENT));
+        if !config.keep_alive {
+            headers.set(Connection::close());
+        }
+
+        let mut builder = ClientBuilder::new();
+        builder.gzip(true)
+            .timeout(config.timeout)
+            .redirect(if config.max_redirects == 0 {
+                RedirectPolicy::none()
+            } else {
+                RedirectPolicy::limited(config.max_redirects)
+            })
+            .default_headers(headers);
+        if let Some(ref ca) = config.ca_bundle {
+            builder.add_root_certificate(try!(Certificate::from_pem(&try!(read_file(ca)))));
+        }
+        if let Some((ref cert, ref password)) = config.client_cert {
+            builder.identity(try!(Identity::from_pkcs12_der(&try!(read_file(cert)), password)));
+        }
+        if config.insecure {
+            builder.danger_disable_hostname_verification();
+        }
+        if let Some(proxy) = config.proxy.reqwest_proxy() {
+            builder.proxy(proxy);
+        }
+
+        Ok(Session {
+            client: try!(builder.build()),
+            credentials: Rc::new(RefCell::new(config.credentials.iter().map(|&(ref o, ref c)| (o.clone(), Some(c.clone()))).collect())),
+            proxy: config.proxy.clone(),
+            proxy_warned: Rc::new(Cell::new(false)),
+            retries: config.retries,
+            retry_backoff: config.retry_backoff,
+        })
+    }
+
+    /// PUT a resource, displaying the upload's progress.
+    pub fn upload(&self, u: Url, f: File) -> Result<Response, Error> {
+        let len = try!(f.metadata()).len();
+        let label = url_file_name(&u).unwrap_or_else(|| u.to_string());
+        self.send(&u, |c| {
+            // Rewind in case the server refused a previous attempt
+            let mut f = try!(f.try_clone());
+            try!(f.seek(SeekFrom::Start(0)));
+
+            let mut req = c.put(u.clone());
+            req.body(Body::sized(ProgressReader::new(f, label.clone(), 0, Some(len)), len));
+            Ok(req)
+        })
+    }
+
+    /// DELETE a resource.
+    pub fn delete(&self, u: Url) -> Result<Response, Error> {
+        self.send(&u, |c| Ok(c.delete(u.clone())))
+    }
+
+    /// GET a resource with the RFSAPI header, auto-unpacking gzip.
+    pub fn download(&self, u: Url) -> Result<Response, Error> {
+        self.really_download(u, true, None, None)
+    }
+
+    /// GET a resource normally, auto-unpacking gzip.
+    pub fn download_raw(&self, u: Url) -> Result<Response, Error> {
+        self.really_download(u, false, None, None)
+    }
+
+    /// GET the specified byte range of a resource normally.
+    ///
+    /// The server may ignore the range, check for `206 Partial Content`.
+    pub fn download_raw_range(&self, u: Url, range: ByteRangeSpec) -> Result<Response, Error> {
+        self.really_download(u, false, Some(range), None)
+    }
+
+    /// GET a resource normally into `dest`, resuming a previous partial download, if any, displaying the download's progress.
+    ///
+    /// The data is written to `dest` with `".part"` appended, which is renamed to `dest` once complete.
+    /// Whenever writing to it stops, its modification time is set to the resource's `Last-Modified` date,
+    /// or it's removed, if the download didn't complete and the server didn't specify one.
+    ///
+    /// If such a file already exists, only the remaining bytes are requested, if the resource wasn't modified since,
+    /// and the whole resource is downloaded anew if the server doesn't honour the range, or returns a different one.
+    ///
+    /// Returns the response's status, nothing is written if it's not a success.
+    pub fn download_resumable(&self, u: Url, dest: &Path) -> Result<StatusCode, Error> {
+        let part = {
+            let mut p = dest.as_os_str().to_owned();
+            p.push(".part");
+            PathBuf::from(p)
+        };
+
+        let have = fs::metadata(&part).ok().and_then(|m| m.modified().ok().map(|lm| (m.len(), lm))).and_then(|(len, lm)| if len != 0 {
+            Some((len, lm))
+        } else {
+            None
+        });
+        let resp = try!(match have {
+            Some((len, lm)) => self.really_download(u.clone(), false, Some(ByteRangeSpec::AllFrom(len)), Some(HttpDate::from(lm))),
+            None => self.download_raw(u.clone()),
+        });
+
+        let status = resp.status();
+        let mut done = 0;
+        let mut out = match (status, have) {
+            (StatusCode::PartialContent, Some((len, _))) => {
+                match resp.headers().get::<ContentRange>() {
+                    Some(&ContentRange(ContentRangeSpec::Bytes { range: Some((start, _)), .. })) if start == len => {}
+                    _ => {
+                        // Not the continuation of the partial file
+                        try!(fs::remove_file(&part));
+                        return self.download_resumable(u, dest);
+                    }
+                }
+                done = len;
+                try!(OpenOptions::new().append(true).open(&part))
+            }
+            (StatusCode::RangeNotSatisfiable, Some(_)) => {
+                // The partial file doesn't match the resource anymore
+                try!(fs::remove_file(&part));
+                return self.download_resumable(u, dest);
+            }
+            (StatusCode::PartialContent, None) => return Err(Error::HttpStatus(u, status)),
+            (s, _) if s.is_success() => try!(File::create(&part)),
+            (s, _) => return Ok(s),
+        };
+        let last_modified = resp.headers()
+            .get::<LastModified>()
+            .and_then(|&LastModified(lm)| SystemTime::from(lm).duration_since(UNIX_EPOCH).ok())
+            .map(|lm| FileTime::from_seconds_since_1970(lm.as_secs(), 0));
+        let total = resp.headers().get::<ContentLength>().map(|&ContentLength(len)| done + len);
+        let copied = io::copy(&mut ProgressReader::new(resp, dest.file_name().unwrap_or(dest.as_os_str()).to_string_lossy(), done, total),
+                              &mut out);
+        drop(out);
+
+        let copied_len = fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+        let complete = copied.is_ok() && total.map(|t| t == copied_len).unwrap_or(true);
+        match last_modified {
+            Some(lm) => try!(filetime::set_file_times(&part, lm, lm)),
+            None if !complete => try!(fs::remove_file(&part)),
+            None => {}
+        }
+        try!(copied);
+        if !complete {
+            return Err(Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                                format!("got {} out of {} bytes of {}", copied_len, total.unwrap_or(0), u))));
+        }
+
+        try!(fs::rename(&part, dest));
+        Ok(status)
+    }
+
+    fn really_download(&self, u: Url, raw: bool, range: Option<ByteRangeSpec>, if_range: Option<HttpDate>) -> Result<Response, Error> {
+        self.send(&u, |c| {
+            let mut req = c.get(u.clone());
+            req.header(RawFsApiHeader(raw));
+            if let Some(ref range) = range {
+                req.header(Range::Bytes(vec![range.clone()]));
+            }
+            if let Some(date) = if_range {
+                req.header(IfRange::Date(date));
+            }
+            Ok(req)
+        })
+    }
+
+    /// Send the request to `u` made by `make`, authenticating it,
+    /// and remaking it if the user provided credentials or it failed transiently.
+    fn send<F: FnMut(&Client) -> Result<RequestBuilder, Error>>(&self, u: &Url, mut make: F) -> Result<Response, Error> {
+        /// Maximal amount of times to ask for credentials for a single request.
+        const MAX_PROMPTS: usize = 3;
+
+        let origin = u.origin();
+        if !self.credentials.borrow().contains_key(&origin) {
+            let stored = u.host_str().and_then(netrc::lookup);
+            self.credentials.borrow_mut().insert(origin.clone(), stored);
+        }
+
+        if !self.proxy_warned.get() && self.proxy.drops_credentials(u) {
+            let _ = writeln!(stderr(),
+                             "<Warning: proxy credentials can't be sent for {} URLs, the proxy may refuse the request>",
+                             u.scheme());
+            self.proxy_warned.set(true);
+        }
+
+        let mut prompts = 0;
+        let mut attempt = 0;
+        loop {
+            let mut req = try!(make(&self.client));
+            if let Some(&Some(ref creds)) = self.credentials.borrow().get(&origin) {
+                creds.apply(&mut req);
+            }
+            if let Some(auth) = self.proxy.authorization_for(u) {
+                req.header(auth);
+            }
+
+            let backoff = retry_backoff(self.retry_backoff, attempt);
+            let (resp, delay, reason) = match req.send() {
+                Ok(resp) => {
+                    match resp.status() {
+                        StatusCode::BadGateway | StatusCode::ServiceUnavailable | StatusCode::GatewayTimeout if attempt < self.retries => {
+                            let delay = resp.headers()
+                                .get_raw("Retry-After")
+                                .and_then(|ra| ra.one())
+                                .and_then(|ra| parse_retry_after(&String::from_utf8_lossy(ra)))
+                                .map(|ra| Duration::from_secs(cmp::min(ra, MAX_RETRY_DELAY)))
+                                .unwrap_or(backoff);
+                            let reason = format!("got status {}", resp.status());
+                            (None, delay, reason)
+                        }
+                        _ => (Some(resp), backoff, String::new()),
+                    }
+                }
+                Err(e) => {
+                    if attempt < self.retries && is_transient(&e) {
+                        (None, backoff, e.to_string())
+                    } else {
+                        return Err(e.into());
+                    }
+                }
+            };
+            let resp = match resp {
+                Some(resp) => resp,
+                None => {
+                    let _ = writeln!(stderr(), "<{}: {}, retrying in {}s...>", u, reason, delay.as_secs());
+                    thread::sleep(delay);
+                    attempt += 1;
+                    continue;
+                }
+            };
+
+            // Credentials are stripped on redirects to other origins, so those can't be answered
+            if resp.status() != StatusCode::Unauthorized || prompts == MAX_PROMPTS || resp.url().origin() != origin {
+                return Ok(resp);
+            }
+            let challenge = match resp.headers().get_raw("WWW-Authenticate").and_then(|c| c.one()) {
+                Some(c) => String::from_utf8_lossy(c).into_owned(),
+                None => return Ok(resp),
+            };
+
+            let creds = try!(prompt_credentials(u, &challenge, self.credentials.borrow().get(&origin).and_then(Option::as_ref)));
+            match creds {
+                Some(creds) => {
+                    self.credentials.borrow_mut().insert(origin.clone(), Some(creds));
+                }
+                None => return Ok(resp),
+            }
+            prompts += 1;
+        }
+    }
+}
+
+
+/// Check whether the request failed in a way that may go away on its own, like a dropped connection or a malformed response.
+fn is_transient(e: &reqwest::Error) -> bool {
+    e.is_http() ||
+    match e.get_ref().and_then(|e| e.downcast_ref::<io::Error>()).map(|e| e.kind()) {
+        Some(io::ErrorKind::ConnectionReset) |
+        Some(io::ErrorKind::ConnectionAborted) |
+        Some(io::ErrorKind::ConnectionRefused) |
+        Some(io::ErrorKind::TimedOut) |
+        Some(io::ErrorKind::BrokenPipe) |
+        Some(io::ErrorKind::UnexpectedEof) |
+        Some(io::ErrorKind::Interrupted) => true,
+        _ => false,
+    }
+}
+
+fn read_file(p: &Path) -> io::Result<Vec<u8>> {
+    let mut data = vec![];
+    try!(try!(File::open(p)).read_to_end(&mut data));
+    Ok(data)
+}
+
+/// Ask the user for credentials to `u` on stderr, in accordance with the `WWW-Authenticate` `challenge`,
+/// if stdin is a terminal.
+///
+/// If `current` credentials are Basic without a password, only the password is asked for.
+///
+/// Returns `Ok(None)` if stdin isn't a terminal or the user cancelled.
+fn prompt_credentials(u: &Url, challenge: &str, current: Option<&Credentials>) -> io::Result<Option<Credentials>> {
+    if !atty::is(Stream::Stdin) {
+        return Ok(None);
+    }
+
+    let input = Input::new();
+    let mut err = stderr();
+    try!(writeln!(err, "<{} requires authentication: {}>", u.origin().unicode_serialization(), challenge));
+
+    if challenge.trim_left().to_lowercase().starts_with("bearer") {
+        try!(write!(err, "Token: "));
+        try!(err.flush());
+        return Ok(try!(input.read_line(false)).map(Credentials::Bearer));
+    }
+
+    let username = match current {
+        Some(&Credentials::Basic { ref username, password: None }) => username.clone(),
+        _ => {
+            try!(write!(err, "Username: "));
+            try!(err.flush());
+            match try!(input.read_line(true)) {
+                Some(u) => u,
+                None => return Ok(None),
+            }
+        }
+    };
+    try!(write!(err, "Password: "));
+    try!(err.flush());
+    Ok(try!(input.read_line(false)).map(|password| {
+        Credentials::Basic {
+            username: username,
+            password: Some(password),
+        }
+    }))
+}
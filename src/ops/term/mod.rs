@@ -1,6 +1,6 @@
 //! Windows and non-Windows terminal utilities.
 //!
-//! All functions in this module return something printable, and you should print their results,
+//! All cursor and screen functions in this module return something printable, and you should print their results,
 //! but do *not* assume that it's the returned text that does stuff,
 //! i.e. do *not* mix terminal manipulation and printing into the same call,
 //! and *always* flush after printing and before using the functions.
@@ -17,6 +17,9 @@
 //! ```
 
 
+use std::cell::Cell;
+use term_size;
+
 #[cfg(target_os="windows")]
 mod windows;
 #[cfg(not(target_os="windows"))]
@@ -26,3 +29,34 @@ mod non_windows;
 pub use self::windows::*;
 #[cfg(not(target_os="windows"))]
 pub use self::non_windows::*;
+
+
+/// Terminal dimensions, re-queried after the terminal is resized.
+///
+/// Resizes are signalled with `SIGWINCH` outside Windows; on Windows, the dimensions are re-queried every time they're gotten.
+#[derive(Debug)]
+pub struct TermSize {
+    size: Cell<(usize, usize)>,
+}
+
+impl TermSize {
+    /// Query the terminal's dimensions and start watching for resizes, see [`watch_resize()`](fn.watch_resize.html).
+    ///
+    /// Returns `None` if the dimensions are unknown.
+    pub fn new() -> Option<TermSize> {
+        term_size::dimensions().map(|size| {
+            watch_resize();
+            TermSize { size: Cell::new(size) }
+        })
+    }
+
+    /// Get the current `(width, height)`.
+    pub fn get(&self) -> (usize, usize) {
+        if take_resized() {
+            if let Some(size) = term_size::dimensions() {
+                self.size.set(size);
+            }
+        }
+        self.size.get()
+    }
+}
@@ -1,8 +1,14 @@
 extern crate tinyfiledialogs;
 
+use std::{mem, ptr};
 use std::ffi::OsStr;
 use std::path::{PathBuf, Path};
+use std::sync::atomic::{AtomicBool, Ordering};
 use self::tinyfiledialogs::{open_file_dialog, save_file_dialog};
+use libc;
+
+
+static RESIZED: AtomicBool = AtomicBool::new(false);
 
 
 /// Move the cursor `n` lines up.
@@ -25,6 +31,42 @@ pub fn show_cursor(show: bool) -> &'static str {
     if show { "\x1B[?25h" } else { "\x1B[?25l" }
 }
 
+/// Switch to/from the alternate screen, which keeps the scrollback intact.
+pub fn alternate_screen(enter: bool) -> &'static str {
+    if enter { "\x1B[?1049h" } else { "\x1B[?1049l" }
+}
+
+/// Clear the screen and move the cursor to its top left corner.
+pub fn clear_screen() -> &'static str {
+    "\x1B[H\x1B[2J"
+}
+
+/// Start noting terminal resizes, signalled by `SIGWINCH`.
+///
+/// Reads blocked when the terminal is resized aren't restarted, but fail with `ErrorKind::Interrupted`.
+pub fn watch_resize() {
+    extern "C" fn on_resize(_: libc::c_int) {
+        RESIZED.store(true, Ordering::SeqCst);
+    }
+
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = on_resize as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGWINCH, &action, ptr::null_mut());
+    }
+}
+
+/// Check whether the terminal was resized since the last [`take_resized()`](fn.take_resized.html).
+pub fn resized() -> bool {
+    RESIZED.load(Ordering::SeqCst)
+}
+
+/// Check whether the terminal was resized since the last call, and forget about it.
+pub fn take_resized() -> bool {
+    RESIZED.swap(false, Ordering::SeqCst)
+}
+
 /// Show a file picker to let user choose where to save a file with the specified filename and optional extension.
 pub fn save_file_picker(filename: &OsStr, extension: Option<&OsStr>) -> Option<PathBuf> {
     let _ = extension;
@@ -6,7 +6,7 @@ use std::ptr::null_mut;
 use std::path::PathBuf;
 use std::ffi::{OsString, OsStr};
 use std::os::windows::ffi::{OsStringExt, OsStrExt};
-use self::kernel32::{SetConsoleCursorPosition, GetConsoleScreenBufferInfo, GetConsoleCursorInfo, SetConsoleCursorInfo, GetStdHandle};
+use self::kernel32::{SetConsoleCursorPosition, GetConsoleScreenBufferInfo, FillConsoleOutputCharacterW, FillConsoleOutputAttribute, GetConsoleCursorInfo, SetConsoleCursorInfo, GetStdHandle};
 use self::winapi::{CONSOLE_SCREEN_BUFFER_INFO, CONSOLE_CURSOR_INFO, STD_OUTPUT_HANDLE, OFN_NOCHANGEDIR, LPOPENFILENAMEW, OPENFILENAMEW, HANDLE, SMALL_RECT,
                    COORD, DWORD, WCHAR, BOOL};
 
@@ -47,6 +47,44 @@ pub fn show_cursor(show: bool) -> &'static str {
     ""
 }
 
+/// The console has no alternate screen, so this does nothing.
+pub fn alternate_screen(enter: bool) -> &'static str {
+    let _ = enter;
+    ""
+}
+
+/// Clear the visible part of the console and move the cursor to its top left corner, keeping the scrollback intact.
+pub fn clear_screen() -> &'static str {
+    if let Some((hand, csbi)) = get_csbi() {
+        let origin = COORD {
+            X: 0,
+            Y: csbi.srWindow.Top,
+        };
+        let size = csbi.dwSize.X as DWORD * (csbi.srWindow.Bottom - csbi.srWindow.Top + 1) as DWORD;
+        let mut written = 0;
+        unsafe {
+            FillConsoleOutputCharacterW(hand, ' ' as WCHAR, size, origin, &mut written);
+            FillConsoleOutputAttribute(hand, csbi.wAttributes, size, origin, &mut written);
+            SetConsoleCursorPosition(hand, origin);
+        }
+    }
+
+    ""
+}
+
+/// Resizes aren't signalled on Windows, so this does nothing.
+pub fn watch_resize() {}
+
+/// Resizes aren't signalled on Windows, so this always returns `false`.
+pub fn resized() -> bool {
+    false
+}
+
+/// Resizes aren't signalled on Windows, so this always returns `true`, to always re-query the dimensions.
+pub fn take_resized() -> bool {
+    true
+}
+
 /// Show a file picker to let user choose where to save a file with the specified filename and optional extension.
 pub fn save_file_picker(filename: &OsStr, extension: Option<&OsStr>) -> Option<PathBuf> {
     let ext: Vec<WCHAR> = if let Some(extension) = extension {
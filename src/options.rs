@@ -12,8 +12,17 @@
 //! ```
 
 
-use clap::{AppSettings, Arg};
+use clap::{self, AppSettings, SubCommand, ErrorKind, Arg};
+use self::super::ops::{SessionConfig, ProxyConfig, KeyBindings, Credentials, ListConfig, netrc};
+use self::super::ops::bookmarks::Bookmarks;
+use self::super::config::Config;
+use self::super::ops::proxy::parse_proxy_url;
+use self::super::util::{parse_byte_range, percent_decode};
+use std::time::Duration;
+use std::path::PathBuf;
+use std::fs;
 use reqwest::Url;
+use url::Origin;
 
 
 /// Representation of the application's all configurable values.
@@ -21,6 +30,61 @@ use reqwest::Url;
 pub struct Options {
     /// Remote directory to start on.
     pub remote_dir: Url,
+    /// What to do with the remote directory.
+    pub command: Command,
+    /// Configuration of the HTTP client used for all requests.
+    pub session: SessionConfig,
+    /// Configuration of the interactive listing.
+    pub list: ListConfig,
+}
+
+/// Action to perform on [`Options::remote_dir`](struct.Options.html#structfield.remote_dir).
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum Command {
+    /// Browse the remote directory interactively.
+    Browse,
+    /// Print the remote directory's listing and exit.
+    List,
+    /// Download the remote file or directory tree and exit.
+    Get {
+        /// Where to save the resource to, `"-"` for stdout, or `None` for its name in the current directory.
+        destination: Option<PathBuf>,
+    },
+    /// Upload the local file or directory tree into the remote directory and exit.
+    Put {
+        /// The file or directory to upload.
+        local: PathBuf,
+    },
+    /// Delete the remote resources and exit.
+    Remove {
+        /// All resources to delete, the first of which is also `remote_dir`.
+        targets: Vec<Url>,
+        /// Whether to delete directories, contents first.
+        recursive: bool,
+        /// Whether to only print what would be deleted.
+        dry_run: bool,
+    },
+    /// Write the remote files to stdout and exit.
+    Cat {
+        /// All files to write, the first of which is also `remote_dir`.
+        targets: Vec<Url>,
+        /// Byte range to write of each file, see [`util::parse_byte_range()`](util/fn.parse_byte_range.html).
+        range: Option<String>,
+    },
+    /// Download new and changed files from the remote directory tree into a local directory and exit.
+    Mirror {
+        /// The directory to mirror into.
+        local: PathBuf,
+        /// Whether to delete local files not present remotely.
+        delete: bool,
+    },
+    /// Upload new and changed files from a local directory tree into the remote directory and exit.
+    Push {
+        /// The directory to upload from.
+        local: PathBuf,
+        /// Whether to delete remote files not present locally.
+        delete: bool,
+    },
 }
 
 impl Options {
@@ -28,21 +92,325 @@ impl Options {
     pub fn parse() -> Options {
         let matches = app_from_crate!("\n")
             .setting(AppSettings::ColoredHelp)
-            .arg(Arg::from_usage("<URL> 'Remote directory to browse'").validator(Options::url_validator))
+            .setting(AppSettings::SubcommandsNegateReqs)
+            .setting(AppSettings::VersionlessSubcommands)
+            .arg(Arg::from_usage("[URL] 'Remote directory to browse, or name of a configured profile'")
+                .validator(Options::url_validator)
+                .required_unless("bookmark"))
+            .arg(Arg::from_usage("-b --bookmark [NAME] 'Bookmarked remote directory to browse'").conflicts_with("URL"))
+            .arg(Arg::from_usage("--config [FILE] 'Configuration file to use, defaults to config.toml in the config directory'").global(true))
+            .arg(Arg::from_usage("--sort [ORDER] 'Order to list entries in, defaults to server order'")
+                .possible_values(&["name", "size", "modified"])
+                .global(true))
+            .arg(Arg::from_usage("--download-dir [DIR] 'Directory to download files into by default'").global(true))
+            .arg(Arg::from_usage("--timeout [SECONDS] 'Time connecting and each read or write of a request may take, \
+                                  a single limit for both, as the HTTP client cannot set them separately, defaults to 30'")
+                .validator(Options::number_validator)
+                .global(true))
+            .arg(Arg::from_usage("--retries [COUNT] 'Maximal amount of times to retry transiently failed requests, defaults to 3'")
+                .validator(Options::number_validator)
+                .global(true))
+            .arg(Arg::from_usage("--retry-backoff [SECONDS] 'Delay before the first retry, doubled for each next one, defaults to 1'")
+                .validator(Options::number_validator)
+                .global(true))
+            .arg(Arg::from_usage("--max-redirects [COUNT] 'Maximal amount of redirects to follow, defaults to 10'")
+                .validator(Options::number_validator)
+                .global(true))
+            .arg(Arg::from_usage("--no-keep-alive 'Close connections after each request'").global(true))
+            .arg(Arg::from_usage("-u --user [USER] 'User to authenticate as with HTTP Basic authentication'").global(true))
+            .arg(Arg::from_usage("-p --password [PASSWORD] 'Password for --user, prompted for if required and not specified'")
+                .requires("user")
+                .global(true))
+            .arg(Arg::from_usage("--token [TOKEN] 'Token to authenticate with HTTP Bearer authentication'")
+                .conflicts_with("user")
+                .global(true))
+            .arg(Arg::from_usage("--ca-bundle [PEM] 'Additional certificate authority to trust'")
+                .validator(Options::path_validator)
+                .global(true))
+            .arg(Arg::from_usage("--client-cert [PKCS12] 'Client certificate and key to present to servers'")
+                .validator(Options::path_validator)
+                .global(true))
+            .arg(Arg::from_usage("--client-cert-password [PASSWORD] 'Password for --client-cert, defaults to none'")
+                .requires("client-cert")
+                .global(true))
+            .arg(Arg::from_usage("-k --insecure 'Skip verifying server hostnames, certificate chains are still verified'").global(true))
+            .arg(Arg::from_usage("--proxy [URL] 'Proxy to route all requests through, overrides the *_proxy environment variables'")
+                .validator(Options::proxy_validator)
+                .global(true))
+            .arg(Arg::from_usage("--proxy-user [USER:PASSWORD] 'Credentials to authenticate to the proxy with, for http:// URLs only'").global(true))
+            .subcommand(SubCommand::with_name("ls")
+                .setting(AppSettings::ColoredHelp)
+                .about("Print a remote directory's listing")
+                .arg(Arg::from_usage("<URL> 'Remote directory to list'").validator(Options::url_validator)))
+            .subcommand(SubCommand::with_name("get")
+                .setting(AppSettings::ColoredHelp)
+                .about("Download a remote file or directory tree")
+                .arg(Arg::from_usage("<URL> 'Remote resource to download'").validator(Options::url_validator))
+                .arg(Arg::from_usage("[DEST] 'Where to save the resource, \"-\" for stdout, defaults to its name in the current directory'")))
+            .subcommand(SubCommand::with_name("put")
+                .setting(AppSettings::ColoredHelp)
+                .about("Upload a local file or directory tree")
+                .arg(Arg::from_usage("<LOCAL> 'Local file or directory to upload'").validator(Options::path_validator))
+                .arg(Arg::from_usage("<URL> 'Remote directory to upload into'").validator(Options::url_validator)))
+            .subcommand(SubCommand::with_name("rm")
+                .setting(AppSettings::ColoredHelp)
+                .about("Delete remote files or directories")
+                .arg(Arg::from_usage("<URL>... 'Remote resources to delete'").validator(Options::url_validator))
+                .arg(Arg::from_usage("-r --recursive 'Delete directories along with their contents'"))
+                .arg(Arg::from_usage("-n --dry-run 'Only print what would be deleted'")))
+            .subcommand(SubCommand::with_name("cat")
+                .setting(AppSettings::ColoredHelp)
+                .about("Write remote files to stdout")
+                .arg(Arg::from_usage("<URL>... 'Remote files to write'").validator(Options::url_validator))
+                .arg(Arg::from_usage("--range [RANGE] 'Only write the specified bytes of each file, as START-END, START- or -LENGTH'")
+                    .validator(Options::range_validator)))
+            .subcommand(SubCommand::with_name("mirror")
+                .setting(AppSettings::ColoredHelp)
+                .about("Download new and changed files from a remote directory tree")
+                .arg(Arg::from_usage("<URL> 'Remote directory to mirror'").validator(Options::url_validator))
+                .arg(Arg::from_usage("<DIR> 'Local directory to mirror into'"))
+                .arg(Arg::from_usage("--delete 'Delete local files not present remotely'")))
+            .subcommand(SubCommand::with_name("push")
+                .setting(AppSettings::ColoredHelp)
+                .about("Upload new and changed files from a local directory tree")
+                .arg(Arg::from_usage("<DIR> 'Local directory to upload from'").validator(Options::path_validator))
+                .arg(Arg::from_usage("<URL> 'Remote directory to upload into'").validator(Options::url_validator))
+                .arg(Arg::from_usage("--delete 'Delete remote files not present locally'")))
             .get_matches();
 
-        let u = matches.value_of("URL").unwrap();
+        let bookmark = matches.value_of("bookmark").map(Options::bookmark_url);
+        let (mut command, u) = match matches.subcommand() {
+            ("ls", Some(matches)) => (Command::List, matches.value_of("URL").unwrap()),
+            ("get", Some(matches)) => (Command::Get { destination: matches.value_of("DEST").map(PathBuf::from) }, matches.value_of("URL").unwrap()),
+            ("put", Some(matches)) => (Command::Put { local: PathBuf::from(matches.value_of("LOCAL").unwrap()) }, matches.value_of("URL").unwrap()),
+            ("rm", Some(matches)) => {
+                (Command::Remove {
+                     targets: matches.values_of("URL").unwrap().map(|u| Options::parse_url(u).unwrap()).collect(),
+                     recursive: matches.is_present("recursive"),
+                     dry_run: matches.is_present("dry-run"),
+                 },
+                 matches.value_of("URL").unwrap())
+            }
+            ("cat", Some(matches)) => {
+                (Command::Cat {
+                     targets: matches.values_of("URL").unwrap().map(|u| Options::parse_url(u).unwrap()).collect(),
+                     range: matches.value_of("range").map(str::to_string),
+                 },
+                 matches.value_of("URL").unwrap())
+            }
+            ("mirror", Some(matches)) => {
+                (Command::Mirror {
+                     local: PathBuf::from(matches.value_of("DIR").unwrap()),
+                     delete: matches.is_present("delete"),
+                 },
+                 matches.value_of("URL").unwrap())
+            }
+            ("push", Some(matches)) => {
+                (Command::Push {
+                     local: PathBuf::from(matches.value_of("DIR").unwrap()),
+                     delete: matches.is_present("delete"),
+                 },
+                 matches.value_of("URL").unwrap())
+            }
+            _ => (Command::Browse, matches.value_of("URL").or_else(|| bookmark.as_ref().map(|b| &b[..])).unwrap()),
+        };
+        // Global arguments may end up in either
+        let sub = matches.subcommand().1.unwrap_or(&matches);
+        let global = |name: &str| sub.value_of(name).or_else(|| matches.value_of(name));
+        let flag = |name: &str| sub.is_present(name) || matches.is_present(name);
+        let default_session = SessionConfig::default();
+
+        if let Some(p) = global("config") {
+            if !PathBuf::from(p).exists() {
+                clap::Error::with_description(&format!("Configuration file {} doesn't exist", p), ErrorKind::InvalidValue).exit();
+            }
+        }
+        let config = match global("config").map(PathBuf::from).or_else(Config::default_path) {
+            Some(p) => Config::load(&p).unwrap_or_else(|e| clap::Error::with_description(&e, ErrorKind::InvalidValue).exit()),
+            None => Config::default(),
+        };
+        let profile = config.profile(u);
+
+        // Credentials in URLs would otherwise end up in listings and logs
+        let mut remote_dir = if config.profiles.contains_key(u) {
+            profile.url.clone().unwrap_or_else(|| {
+                clap::Error::with_description(&format!("Profile {} doesn't specify a url", u), ErrorKind::InvalidValue).exit()
+            })
+        } else {
+            Options::parse_url(u).unwrap()
+        };
+        // Each set of credentials is only ever sent to its own origin, the first one specified for an origin wins
+        let mut credentials = vec![];
+        let cli_credentials = if let Some(token) = global("token") {
+            Some(Credentials::Bearer(token.to_string()))
+        } else if let Some(user) = global("user") {
+            Some(Credentials::Basic {
+                username: user.to_string(),
+                password: global("password").map(str::to_string),
+            })
+        } else {
+            None
+        };
+        Options::add_credentials(&mut credentials, &remote_dir, cli_credentials);
+        let url_credentials = Options::strip_credentials(&mut remote_dir);
+        Options::add_credentials(&mut credentials, &remote_dir, url_credentials);
+        match command {
+            Command::Remove { ref mut targets, .. } |
+            Command::Cat { ref mut targets, .. } => {
+                for t in targets {
+                    let url_credentials = Options::strip_credentials(t);
+                    Options::add_credentials(&mut credentials, t, url_credentials);
+                }
+            }
+            _ => {}
+        }
+
+        let profile_credentials = if let Some(ref token) = profile.token {
+            Some(Credentials::Bearer(token.clone()))
+        } else if let Some(ref user) = profile.user {
+            Some(Credentials::Basic {
+                username: user.clone(),
+                password: profile.password.clone(),
+            })
+        } else {
+            None
+        };
+        Options::add_credentials(&mut credentials, &remote_dir, profile_credentials);
+        // Other hosts' stored credentials are looked up by the session as they're requested
+        let stored_credentials = profile.credentials.as_ref().and_then(|c| netrc::lookup(c));
+        Options::add_credentials(&mut credentials, &remote_dir, stored_credentials);
+
+        let mut proxy = ProxyConfig::from_env();
+        if let Some(p) = global("proxy").or_else(|| profile.proxy.as_ref().map(|p| &p[..])) {
+            proxy = proxy.with_proxy(parse_proxy_url(p).unwrap_or_else(|| {
+                clap::Error::with_description(&format!("{} is not a valid proxy URL", p), ErrorKind::InvalidValue).exit()
+            }));
+        }
+        if let Some(user) = global("proxy-user") {
+            if proxy.http.is_none() && proxy.https.is_none() {
+                clap::Error::with_description("--proxy-user requires a proxy, set with --proxy or the *_proxy environment variables",
+                                              ErrorKind::MissingRequiredArgument)
+                    .exit();
+            }
+            // HTTPS requests are tunnelled through the proxy, which can't carry the credentials
+            let https_target = match command {
+                Command::Remove { ref targets, .. } |
+                Command::Cat { ref targets, .. } => targets.iter().any(|t| t.scheme() == "https"),
+                _ => false,
+            };
+            if https_target || remote_dir.scheme() == "https" {
+                clap::Error::with_description("--proxy-user is only supported for http:// URLs", ErrorKind::ArgumentConflict).exit();
+            }
+            let (username, password) = match user.find(':') {
+                Some(idx) => (&user[..idx], Some(&user[idx + 1..])),
+                None => (user, None),
+            };
+            for p in proxy.http.iter_mut().chain(proxy.https.iter_mut()) {
+                let _ = p.set_username(username);
+                let _ = p.set_password(password);
+            }
+        }
+
+        let client_cert_password = global("client-cert-password").map(str::to_string).or(profile.client_cert_password).unwrap_or_default();
+        let mut keys = KeyBindings::default();
+        for (action, &key) in &profile.keys {
+            // Validated when parsing the config
+            let _ = keys.set(action, key);
+        }
+
         Options {
-            remote_dir: Url::parse(u)
-                .or_else(|_| Url::parse(&format!("http://{}", u)))
-                .unwrap(),
+            remote_dir: remote_dir,
+            command: command,
+            session: SessionConfig {
+                timeout: global("timeout")
+                    .map(|t| t.parse().unwrap())
+                    .or(profile.timeout)
+                    .map(Duration::from_secs)
+                    .unwrap_or(default_session.timeout),
+                retries: global("retries").map(|r| r.parse().unwrap()).or(profile.retries).unwrap_or(default_session.retries),
+                retry_backoff: global("retry-backoff")
+                    .map(|b| b.parse().unwrap())
+                    .or(profile.retry_backoff)
+                    .map(Duration::from_secs)
+                    .unwrap_or(default_session.retry_backoff),
+                max_redirects: global("max-redirects").map(|r| r.parse().unwrap()).or(profile.max_redirects).unwrap_or(default_session.max_redirects),
+                keep_alive: !flag("no-keep-alive"),
+                credentials: credentials,
+                ca_bundle: global("ca-bundle").map(PathBuf::from).or(profile.ca_bundle),
+                client_cert: global("client-cert").map(PathBuf::from).or(profile.client_cert).map(|c| (c, client_cert_password)),
+                insecure: flag("insecure") || profile.insecure.unwrap_or(false),
+                proxy: proxy,
+            },
+            list: ListConfig {
+                sort: global("sort").map(|s| s.parse().unwrap()).or(profile.sort),
+                keys: keys,
+                download_dir: global("download-dir").map(PathBuf::from).or(profile.download_dir),
+                bookmarks: Bookmarks::default_path(),
+            },
         }
     }
 
-    fn url_validator(s: String) -> Result<(), String> {
-        Url::parse(&s)
+    /// Get the URL bookmarked with the specified name, exiting if there's no such bookmark.
+    fn bookmark_url(name: &str) -> String {
+        let bookmarks = Bookmarks::default_path()
+            .map(|p| Bookmarks::load(&p))
+            .unwrap_or_else(|| Ok(Bookmarks::default()))
+            .unwrap_or_else(|e| clap::Error::with_description(&e.to_string(), ErrorKind::Io).exit());
+        match bookmarks.get(name) {
+            Some(u) => u.to_string(),
+            None => clap::Error::with_description(&format!("No bookmark named {}", name), ErrorKind::InvalidValue).exit(),
+        }
+    }
+
+    /// Remove the userinfo from the specified URL, returning it as Basic credentials, if any.
+    fn strip_credentials(u: &mut Url) -> Option<Credentials> {
+        if u.username().is_empty() {
+            return None;
+        }
+
+        let decode = |s: &str| percent_decode(s).map(|s| s.into_owned()).unwrap_or_else(|| s.to_string());
+        let creds = Credentials::Basic {
+            username: decode(u.username()),
+            password: u.password().map(&decode),
+        };
+        let _ = u.set_username("");
+        let _ = u.set_password(None);
+        Some(creds)
+    }
+
+    /// Scope the specified credentials, if any, to the URL's origin, unless that already has some.
+    fn add_credentials(all: &mut Vec<(Origin, Credentials)>, u: &Url, creds: Option<Credentials>) {
+        if let Some(creds) = creds {
+            let origin = u.origin();
+            if !all.iter().any(|&(ref o, _)| *o == origin) {
+                all.push((origin, creds));
+            }
+        }
+    }
+
+    fn parse_url(s: &str) -> Result<Url, String> {
+        Url::parse(s)
             .or_else(|_| Url::parse(&format!("http://{}", s)))
-            .map(|_| ())
             .map_err(|e| e.to_string())
     }
+
+    fn range_validator(s: String) -> Result<(), String> {
+        parse_byte_range(&s).map(|_| ()).ok_or_else(|| format!("{} is not a valid byte range", s))
+    }
+
+    fn number_validator(s: String) -> Result<(), String> {
+        s.parse::<u64>().map(|_| ()).map_err(|e| format!("{}: {}", s, e))
+    }
+
+    fn path_validator(s: String) -> Result<(), String> {
+        fs::metadata(&s).map(|_| ()).map_err(|e| format!("{}: {}", s, e))
+    }
+
+    fn proxy_validator(s: String) -> Result<(), String> {
+        parse_proxy_url(&s).map(|_| ()).ok_or_else(|| format!("{} is not a valid proxy URL", s))
+    }
+
+    fn url_validator(s: String) -> Result<(), String> {
+        Options::parse_url(&s).map(|_| ())
+    }
 }
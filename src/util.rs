@@ -1,8 +1,12 @@
 //! Module containing various utility functions.
 
 
+use reqwest::header::ByteRangeSpec;
 use time::{self, Tm};
 use std::borrow::Cow;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::env;
 use std::{iter, f64, cmp};
 use url::{percent_encoding, Url};
 
@@ -14,6 +18,10 @@ pub static USER_AGENT: &str = concat!("D'Oh/", env!("CARGO_PKG_VERSION"));
 pub const GETCH_ENTER: u8 = b'\r';
 /// Byte returned by `getch()` for Escape.
 pub const GETCH_ESC: u8 = b'\x1B';
+/// Byte returned by `getch()` for Backspace on Windows.
+pub const GETCH_BACKSPACE: u8 = b'\x08';
+/// Byte returned by `getch()` for Backspace on most other terminals.
+pub const GETCH_BACKSPACE_DEL: u8 = b'\x7F';
 /// First byte returned by `getch()` for special characters.
 pub const GETCH_SPECIAL_PREFIX: u8 = 224;
 /// Second byte returned by `getch()` for up arrow key.
@@ -32,6 +40,9 @@ pub const GETCH_DELETE: u8 = 83;
 /// Provided by @Ell, so flame him.
 /// <span title=":noel:">![:noel:](https://cdn.discordapp.com/emojis/230277422006796288.png)</span>
 pub const TAB_WIDTH: usize = 4;
+
+/// Maximal delay before retrying a request, in seconds, unless the initial backoff is longer.
+pub const MAX_RETRY_DELAY: u64 = 120;
 lazy_static! {
     /// Filler to replace tabs with of length [`TAB_WIDTH`](constant.TAB_WIDTH.html).
     pub static ref TAB_SPACING: String = iter::repeat(' ').take(TAB_WIDTH).collect();
@@ -160,6 +171,24 @@ pub fn human_readable_size(s: u64) -> String {
     }
 }
 
+/// Construct string representing a human-readable duration in seconds, as `[H:]M:SS`.
+///
+/// # Examples
+///
+/// ```
+/// # use doh::util::human_readable_duration;
+/// assert_eq!(human_readable_duration(7), "0:07");
+/// assert_eq!(human_readable_duration(127), "2:07");
+/// assert_eq!(human_readable_duration(3723), "1:02:03");
+/// ```
+pub fn human_readable_duration(s: u64) -> String {
+    if s >= 60 * 60 {
+        format!("{}:{:02}:{:02}", s / (60 * 60), s / 60 % 60, s % 60)
+    } else {
+        format!("{}:{:02}", s / 60, s % 60)
+    }
+}
+
 /// Get a URL that is canonically considered a "parent" to the specified one, or the specified URL if it is a root URL.
 ///
 /// # Examples
@@ -178,6 +207,77 @@ pub fn parent_url(u: &Url) -> Url {
     p[0..p[0..p.len() - ((p.chars().last() == Some('/')) as usize)].rfind('/').unwrap()].parse().unwrap_or_else(|_| u.clone())
 }
 
+/// Parse a single byte range in the `"START-END"`, `"START-"` or `"-LENGTH"` format, like in the `Range` header.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate reqwest;
+/// # extern crate doh;
+/// # use reqwest::header::ByteRangeSpec;
+/// # use doh::util::parse_byte_range;
+/// # fn main() {
+/// assert_eq!(parse_byte_range("0-499"), Some(ByteRangeSpec::FromTo(0, 499)));
+/// assert_eq!(parse_byte_range("9500-"), Some(ByteRangeSpec::AllFrom(9500)));
+/// assert_eq!(parse_byte_range("-500"), Some(ByteRangeSpec::Last(500)));
+/// assert_eq!(parse_byte_range("500-499"), None);
+/// assert_eq!(parse_byte_range("-"), None);
+/// # }
+/// ```
+pub fn parse_byte_range(s: &str) -> Option<ByteRangeSpec> {
+    let dash = match s.find('-') {
+        Some(d) => d,
+        None => return None,
+    };
+    let (start, end) = (&s[..dash], &s[dash + 1..]);
+    match (start.parse::<u64>().ok(), end.parse::<u64>().ok()) {
+        (Some(start), Some(end)) if start <= end => Some(ByteRangeSpec::FromTo(start, end)),
+        (Some(start), None) if end.is_empty() => Some(ByteRangeSpec::AllFrom(start)),
+        (None, Some(len)) if start.is_empty() => Some(ByteRangeSpec::Last(len)),
+        _ => None,
+    }
+}
+
+/// Get the URL to the directory at the specified URL, i.e. make sure its path ends with a `'/'`.
+///
+/// Without it, [`Url::join()`](https://docs.rs/url/1/url/struct.Url.html#method.join) would replace the last segment
+/// instead of appending to it.
+///
+/// # Examples
+///
+/// ```
+/// # use doh::util::directory_url;
+/// assert_eq!(directory_url("https://google.com/search".parse().unwrap()),
+///            "https://google.com/search/".parse().unwrap());
+/// assert_eq!(directory_url("https://google.com/search/".parse().unwrap()),
+///            "https://google.com/search/".parse().unwrap());
+/// ```
+pub fn directory_url(mut u: Url) -> Url {
+    if !u.path().ends_with('/') {
+        let p = format!("{}/", u.path());
+        u.set_path(&p);
+    }
+    u
+}
+
+/// Get the percent-decoded last nonempty segment of the specified URL's path, or `None` for root URLs.
+///
+/// # Examples
+///
+/// ```
+/// # use doh::util::url_file_name;
+/// assert_eq!(url_file_name(&"https://google.com/search/%D0%B0%D1%81%D0%B4%D1%84.txt".parse().unwrap()),
+///            Some("асдф.txt".to_string()));
+/// assert_eq!(url_file_name(&"https://google.com/search/".parse().unwrap()),
+///            Some("search".to_string()));
+/// assert_eq!(url_file_name(&"https://google.com".parse().unwrap()), None);
+/// ```
+pub fn url_file_name(u: &Url) -> Option<String> {
+    u.path_segments()
+        .and_then(|ss| ss.filter(|s| !s.is_empty()).last())
+        .and_then(|s| percent_decode(s).map(|s| s.into_owned()))
+}
+
 /// Decode a percent-encoded string (like a part of a URL).
 ///
 /// # Example
@@ -191,3 +291,62 @@ pub fn parent_url(u: &Url) -> Url {
 pub fn percent_decode(s: &str) -> Option<Cow<str>> {
     percent_encoding::percent_decode(s.as_bytes()).decode_utf8().ok()
 }
+
+/// Parse the value of a `Retry-After` header into the amount of seconds to wait.
+///
+/// Both delays in seconds and HTTP dates are accepted, dates in the past yield `0`.
+///
+/// # Examples
+///
+/// ```
+/// # use doh::util::parse_retry_after;
+/// assert_eq!(parse_retry_after("120"), Some(120));
+/// assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(0));
+/// assert_eq!(parse_retry_after("soon"), None);
+/// ```
+pub fn parse_retry_after(s: &str) -> Option<u64> {
+    let s = s.trim();
+    s.parse().ok().or_else(|| {
+        time::strptime(s, "%a, %d %b %Y %H:%M:%S GMT")
+            .ok()
+            .map(|t| cmp::max((t.to_timespec() - time::get_time()).num_seconds(), 0) as u64)
+    })
+}
+
+/// Get the delay before the retry following the specified attempt (counted from `0`),
+/// which doubles the `initial` one with each attempt,
+/// up to [`MAX_RETRY_DELAY`](constant.MAX_RETRY_DELAY.html) seconds or `initial`, whichever is longer.
+///
+/// # Examples
+///
+/// ```
+/// # use doh::util::retry_backoff;
+/// # use std::time::Duration;
+/// assert_eq!(retry_backoff(Duration::from_secs(1), 0), Duration::from_secs(1));
+/// assert_eq!(retry_backoff(Duration::from_secs(1), 3), Duration::from_secs(8));
+/// assert_eq!(retry_backoff(Duration::from_secs(1), 10), Duration::from_secs(120));
+/// ```
+pub fn retry_backoff(initial: Duration, attempt: usize) -> Duration {
+    let max = cmp::max(initial, Duration::from_secs(MAX_RETRY_DELAY));
+    initial.checked_mul(1 << cmp::min(attempt, 16))
+        .map(|b| cmp::min(b, max))
+        .unwrap_or(max)
+}
+
+/// Get the current user's home directory from the environment, if set.
+pub fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .and_then(|h| if h.is_empty() { None } else { Some(PathBuf::from(h)) })
+}
+
+/// Get the directory doh's configuration is stored in, if it can be determined.
+///
+/// That's `$XDG_CONFIG_HOME/doh`, falling back to `%APPDATA%\doh` and `~/.config/doh`.
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .or_else(|| env::var_os("APPDATA"))
+        .and_then(|d| if d.is_empty() { None } else { Some(PathBuf::from(d)) })
+        .or_else(|| home_dir().map(|h| h.join(".config")))
+        .map(|d| d.join("doh"))
+}
@@ -0,0 +1,125 @@
+use doh::ops::SortOrder;
+use doh::Config;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::path::PathBuf;
+use std::io::Write;
+use std::{env, fs};
+
+
+fn temp_file(name: &str, content: &str) -> PathBuf {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos();
+    let p = env::temp_dir().join(format!("doh-config-test-{}-{}.toml", name, nanos));
+    fs::File::create(&p).unwrap().write_all(content.as_bytes()).unwrap();
+    p
+}
+
+
+#[test]
+fn empty() {
+    assert_eq!(Config::parse("").unwrap(), Config::default());
+}
+
+#[test]
+fn defaults() {
+    let config = Config::parse("timeout = 10\nretries = 5\nretry_backoff = 2\nsort = \"size\"\ninsecure = true\n[keys]\nquit = \"x\"")
+        .unwrap();
+    assert_eq!(config.defaults.timeout, Some(10));
+    assert_eq!(config.defaults.retries, Some(5));
+    assert_eq!(config.defaults.retry_backoff, Some(2));
+    assert_eq!(config.defaults.sort, Some(SortOrder::Size));
+    assert_eq!(config.defaults.insecure, Some(true));
+    assert_eq!(config.defaults.keys.get("quit"), Some(&'x'));
+    assert!(config.profiles.is_empty());
+}
+
+#[test]
+fn profile_selection() {
+    let config = Config::parse("timeout = 10\n\
+                                retries = 1\n\
+                                [keys]\n\
+                                quit = \"x\"\n\
+                                download = \"s\"\n\
+                                [profiles.work]\n\
+                                url = \"https://files.example.com/shared/\"\n\
+                                user = \"me\"\n\
+                                password = \"hunter2\"\n\
+                                timeout = 60\n\
+                                [profiles.work.keys]\n\
+                                quit = \"e\"")
+        .unwrap();
+
+    let work = config.profile("work");
+    assert_eq!(work.url, Some("https://files.example.com/shared/".parse().unwrap()));
+    assert_eq!(work.user, Some("me".to_string()));
+    assert_eq!(work.password, Some("hunter2".to_string()));
+    assert_eq!(work.timeout, Some(60));
+    assert_eq!(work.retries, Some(1));
+    assert_eq!(work.keys.get("quit"), Some(&'e'));
+    assert_eq!(work.keys.get("download"), Some(&'s'));
+
+    let other = config.profile("https://other.example.com/");
+    assert_eq!(other, config.defaults);
+    assert_eq!(other.url, None);
+    assert_eq!(other.user, None);
+    assert_eq!(other.timeout, Some(10));
+}
+
+#[test]
+fn credentials_only_in_profiles() {
+    for key in &["user", "password", "token", "credentials"] {
+        let err = Config::parse(&format!("{} = \"x\"", key)).unwrap_err();
+        assert!(err.starts_with(key), "{}", err);
+    }
+    assert!(Config::parse("[profiles.p]\nurl = \"https://example.com\"\ntoken = \"x\"").is_ok());
+}
+
+#[test]
+fn unknown_keys() {
+    assert!(Config::parse("retires = 3").unwrap_err().starts_with("retires"));
+    assert!(Config::parse("[profiles.p]\nretires = 3").unwrap_err().starts_with("profiles.p.retires"));
+    assert!(Config::parse("[profiles.p.profiles.q]\nretries = 3").is_err());
+}
+
+#[test]
+fn bad_values() {
+    assert!(Config::parse("timeout = -1").is_err());
+    assert!(Config::parse("timeout = \"10\"").is_err());
+    assert!(Config::parse("insecure = 1").is_err());
+    assert!(Config::parse("sort = \"date\"").is_err());
+    assert!(Config::parse("[profiles.p]\nurl = \"not a url\"").is_err());
+    assert!(Config::parse("[keys]\nquit = \"xy\"").is_err());
+    assert!(Config::parse("[keys]\nfly = \"f\"").is_err());
+    assert!(Config::parse("profiles = 1").is_err());
+    assert!(Config::parse("not toml").is_err());
+}
+
+#[test]
+fn secrets() {
+    assert!(!Config::parse("[profiles.p]\nuser = \"me\"").unwrap().has_secrets());
+    assert!(Config::parse("[profiles.p]\nuser = \"me\"\npassword = \"x\"").unwrap().has_secrets());
+    assert!(Config::parse("[profiles.p]\ntoken = \"x\"").unwrap().has_secrets());
+    assert!(Config::parse("client_cert_password = \"x\"").unwrap().has_secrets());
+}
+
+#[test]
+fn load_nonexistant() {
+    assert_eq!(Config::load(&env::temp_dir().join("doh-config-test-nonexistant.toml")).unwrap(), Config::default());
+}
+
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn load_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let public = temp_file("public", "timeout = 10");
+    fs::set_permissions(&public, fs::Permissions::from_mode(0o644)).unwrap();
+    assert_eq!(Config::load(&public).unwrap().defaults.timeout, Some(10));
+    fs::remove_file(&public).unwrap();
+
+    let secret = temp_file("secret", "[profiles.p]\ntoken = \"x\"");
+    fs::set_permissions(&secret, fs::Permissions::from_mode(0o644)).unwrap();
+    assert!(Config::load(&secret).is_err());
+    fs::set_permissions(&secret, fs::Permissions::from_mode(0o600)).unwrap();
+    assert_eq!(Config::load(&secret).unwrap().profile("p").token, Some("x".to_string()));
+    fs::remove_file(&secret).unwrap();
+}
@@ -1,7 +1,10 @@
 extern crate reqwest;
 extern crate getch;
 extern crate time;
+extern crate filetime;
 extern crate doh;
 
 
 mod util;
+mod ops;
+mod config;
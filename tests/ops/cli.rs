@@ -0,0 +1,113 @@
+use doh::ops::cli::{local_outdated, remote_outdated};
+use doh::ops::RemoteFile;
+use filetime::{self, FileTime};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::path::PathBuf;
+use std::io::Write;
+use std::{env, fs};
+use time;
+
+
+/// Modification time of the local files, in seconds since the UNIX epoch.
+const MTIME: i64 = 1500000000;
+
+
+fn local_file(name: &str, content: &[u8]) -> PathBuf {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos();
+    let p = env::temp_dir().join(format!("doh-cli-test-{}-{}", name, nanos));
+    fs::File::create(&p).unwrap().write_all(content).unwrap();
+    let mtime = FileTime::from_seconds_since_1970(MTIME as u64, 0);
+    filetime::set_file_times(&p, mtime, mtime).unwrap();
+    p
+}
+
+fn remote_file(size: u64, modified: Option<i64>) -> RemoteFile {
+    RemoteFile {
+        full_name: "file".to_string(),
+        size: Some(size),
+        human_size: None,
+        last_modified: modified.map(|m| time::at(time::Timespec::new(m, 0))),
+    }
+}
+
+
+#[test]
+fn local_missing() {
+    assert!(local_outdated(&remote_file(0, Some(MTIME)), &env::temp_dir().join("doh-cli-test-nonexistant")));
+}
+
+#[test]
+fn local_up_to_date() {
+    let local = local_file("local-up-to-date", b"data");
+    assert!(!local_outdated(&remote_file(4, Some(MTIME)), &local));
+    assert!(!local_outdated(&remote_file(4, Some(MTIME - 60)), &local));
+    fs::remove_file(&local).unwrap();
+}
+
+#[test]
+fn local_older() {
+    let local = local_file("local-older", b"data");
+    assert!(local_outdated(&remote_file(4, Some(MTIME + 1)), &local));
+    fs::remove_file(&local).unwrap();
+}
+
+#[test]
+fn local_size_differs() {
+    let local = local_file("local-size-differs", b"data");
+    assert!(local_outdated(&remote_file(5, Some(MTIME)), &local));
+    assert!(local_outdated(&remote_file(3, Some(MTIME - 60)), &local));
+    fs::remove_file(&local).unwrap();
+}
+
+#[test]
+fn local_remote_time_unknown() {
+    let local = local_file("local-time-unknown", b"data");
+    assert!(local_outdated(&remote_file(4, None), &local));
+    fs::remove_file(&local).unwrap();
+}
+
+#[test]
+fn remote_missing() {
+    let local = local_file("remote-missing", b"data");
+    assert!(remote_outdated(&local, None));
+    fs::remove_file(&local).unwrap();
+}
+
+#[test]
+fn remote_up_to_date() {
+    let local = local_file("remote-up-to-date", b"data");
+    assert!(!remote_outdated(&local, Some(&remote_file(4, Some(MTIME)))));
+    assert!(!remote_outdated(&local, Some(&remote_file(4, Some(MTIME + 60)))));
+    fs::remove_file(&local).unwrap();
+}
+
+#[test]
+fn remote_older() {
+    let local = local_file("remote-older", b"data");
+    assert!(remote_outdated(&local, Some(&remote_file(4, Some(MTIME - 1)))));
+    fs::remove_file(&local).unwrap();
+}
+
+#[test]
+fn remote_size_differs() {
+    let local = local_file("remote-size-differs", b"data");
+    assert!(remote_outdated(&local, Some(&remote_file(5, Some(MTIME + 60)))));
+    fs::remove_file(&local).unwrap();
+}
+
+#[test]
+fn remote_time_unknown() {
+    let local = local_file("remote-time-unknown", b"data");
+    assert!(remote_outdated(&local, Some(&remote_file(4, None))));
+    fs::remove_file(&local).unwrap();
+}
+
+#[test]
+fn mirrored_not_pushed_back() {
+    // A freshly mirrored file has the remote modification time, and mustn't be uploaded again
+    let local = local_file("mirrored", b"data");
+    let remote = remote_file(4, Some(MTIME));
+    assert!(!local_outdated(&remote, &local));
+    assert!(!remote_outdated(&local, Some(&remote)));
+    fs::remove_file(&local).unwrap();
+}
@@ -0,0 +1,83 @@
+use doh::ops::filter::{Filter, FilterMode};
+
+
+fn matches(pattern: &str, mode: FilterMode, name: &str) -> bool {
+    Filter {
+            pattern: pattern.to_string(),
+            mode: mode,
+        }
+        .matches(name)
+}
+
+
+#[test]
+fn empty_matches_all() {
+    for &mode in &[FilterMode::Substring, FilterMode::Glob, FilterMode::Fuzzy] {
+        assert!(matches("", mode, "anything"));
+        assert!(matches("", mode, ""));
+    }
+}
+
+#[test]
+fn substring() {
+    assert!(matches("ME.m", FilterMode::Substring, "README.md"));
+    assert!(!matches("*.md", FilterMode::Substring, "README.md"));
+}
+
+#[test]
+fn glob_literal() {
+    assert!(matches("readme.md", FilterMode::Glob, "README.md"));
+    assert!(!matches("readme", FilterMode::Glob, "README.md"));
+    assert!(!matches("readme.md.", FilterMode::Glob, "README.md"));
+}
+
+#[test]
+fn glob_question_mark() {
+    assert!(matches("?.rs", FilterMode::Glob, "a.rs"));
+    assert!(!matches("?.rs", FilterMode::Glob, ".rs"));
+    assert!(!matches("?.rs", FilterMode::Glob, "ab.rs"));
+}
+
+#[test]
+fn glob_star() {
+    assert!(matches("*", FilterMode::Glob, ""));
+    assert!(matches("*", FilterMode::Glob, "src/"));
+    assert!(matches("*.rs", FilterMode::Glob, "main.rs"));
+    assert!(matches("m*n.rs", FilterMode::Glob, "main.rs"));
+    assert!(matches("**a**", FilterMode::Glob, "a"));
+    assert!(!matches("*.rs", FilterMode::Glob, "main.rs.bak"));
+}
+
+#[test]
+fn glob_backtracking() {
+    assert!(matches("*ab", FilterMode::Glob, "aab"));
+    assert!(matches("*a*b", FilterMode::Glob, "xaxxab"));
+    assert!(matches("a*b*c", FilterMode::Glob, "abbbcbc"));
+    assert!(!matches("a*b*c", FilterMode::Glob, "abcb"));
+}
+
+#[test]
+fn fuzzy() {
+    assert!(matches("cgtml", FilterMode::Fuzzy, "Cargo.toml"));
+    assert!(matches("Cargo.toml", FilterMode::Fuzzy, "cargo.toml"));
+    assert!(!matches("tomlc", FilterMode::Fuzzy, "Cargo.toml"));
+    assert!(!matches("oo", FilterMode::Fuzzy, "Cargo.tml"));
+}
+
+#[test]
+fn non_ascii() {
+    assert!(matches("ŻÓŁ", FilterMode::Substring, "żółw.txt"));
+    assert!(matches("ż?ł*", FilterMode::Glob, "ŻÓŁW.txt"));
+    assert!(matches("żw", FilterMode::Fuzzy, "żółw"));
+}
+
+#[test]
+fn mode_cycle() {
+    let mut mode = FilterMode::default();
+    assert_eq!(mode, FilterMode::Substring);
+    for _ in 0..3 {
+        mode = mode.next();
+    }
+    assert_eq!(mode, FilterMode::Substring);
+    assert_eq!(FilterMode::Glob.to_string(), "glob");
+}
@@ -0,0 +1,138 @@
+use doh::ops::input::{KeyPress, Key, decode_key};
+
+
+fn decode(bytes: &[u8]) -> KeyPress {
+    let mut rest = bytes[1..].iter().cloned();
+    decode_key(bytes[0], |_| Ok(rest.next())).unwrap()
+}
+
+fn with(key: Key, ctrl: bool, alt: bool, shift: bool) -> KeyPress {
+    KeyPress {
+        ctrl: ctrl,
+        alt: alt,
+        shift: shift,
+        ..KeyPress::plain(key)
+    }
+}
+
+
+#[test]
+fn plain() {
+    assert_eq!(decode(b"a"), KeyPress::plain(Key::Char('a')));
+    assert_eq!(decode(b"\r"), KeyPress::plain(Key::Enter));
+    assert_eq!(decode(b"\n"), KeyPress::plain(Key::Enter));
+    assert_eq!(decode(b"\t"), KeyPress::plain(Key::Tab));
+    assert_eq!(decode(b"\x08"), KeyPress::plain(Key::Backspace));
+    assert_eq!(decode(b"\x7F"), KeyPress::plain(Key::Backspace));
+    assert_eq!(decode("ż".as_bytes()), KeyPress::plain(Key::Char('ż')));
+    assert_eq!(decode(b"\x01"), with(Key::Char('a'), true, false, false));
+    assert_eq!(decode(b"\xFF"), KeyPress::plain(Key::Unknown));
+}
+
+#[test]
+fn lone_escape() {
+    let mut waits = vec![];
+    let press = decode_key(b'\x1B', |wait| {
+            waits.push(wait);
+            Ok(None)
+        })
+        .unwrap();
+    assert_eq!(press, KeyPress::plain(Key::Escape));
+    assert_eq!(waits, vec![false]);
+}
+
+#[test]
+fn escape_prefixed() {
+    assert_eq!(decode(b"\x1Bx"), with(Key::Char('x'), false, true, false));
+    assert_eq!(decode(b"\x1B\x1B"), with(Key::Escape, false, true, false));
+    assert_eq!(decode(b"\x1B\r"), with(Key::Enter, false, true, false));
+}
+
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn escape_sequence_waits() {
+    let mut rest = b"[A".iter().cloned();
+    let mut waits = vec![];
+    let press = decode_key(b'\x1B', |wait| {
+            waits.push(wait);
+            Ok(rest.next())
+        })
+        .unwrap();
+    assert_eq!(press, KeyPress::plain(Key::Up));
+    assert_eq!(waits, vec![false, true]);
+}
+
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn csi() {
+    assert_eq!(decode(b"\x1B[A"), KeyPress::plain(Key::Up));
+    assert_eq!(decode(b"\x1B[B"), KeyPress::plain(Key::Down));
+    assert_eq!(decode(b"\x1B[C"), KeyPress::plain(Key::Right));
+    assert_eq!(decode(b"\x1B[D"), KeyPress::plain(Key::Left));
+    assert_eq!(decode(b"\x1B[H"), KeyPress::plain(Key::Home));
+    assert_eq!(decode(b"\x1B[F"), KeyPress::plain(Key::End));
+    assert_eq!(decode(b"\x1B[1~"), KeyPress::plain(Key::Home));
+    assert_eq!(decode(b"\x1B[2~"), KeyPress::plain(Key::Insert));
+    assert_eq!(decode(b"\x1B[3~"), KeyPress::plain(Key::Delete));
+    assert_eq!(decode(b"\x1B[4~"), KeyPress::plain(Key::End));
+    assert_eq!(decode(b"\x1B[5~"), KeyPress::plain(Key::PageUp));
+    assert_eq!(decode(b"\x1B[6~"), KeyPress::plain(Key::PageDown));
+    assert_eq!(decode(b"\x1B[11~"), KeyPress::plain(Key::F(1)));
+    assert_eq!(decode(b"\x1B[15~"), KeyPress::plain(Key::F(5)));
+    assert_eq!(decode(b"\x1B[17~"), KeyPress::plain(Key::F(6)));
+    assert_eq!(decode(b"\x1B[21~"), KeyPress::plain(Key::F(10)));
+    assert_eq!(decode(b"\x1B[23~"), KeyPress::plain(Key::F(11)));
+    assert_eq!(decode(b"\x1B[24~"), KeyPress::plain(Key::F(12)));
+    assert_eq!(decode(b"\x1B[99~"), KeyPress::plain(Key::Unknown));
+    assert_eq!(decode(b"\x1B[Z"), with(Key::Tab, false, false, true));
+}
+
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn csi_truncated() {
+    assert_eq!(decode(b"\x1B[1;5"), KeyPress::plain(Key::Unknown));
+}
+
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn ss3() {
+    assert_eq!(decode(b"\x1BOA"), KeyPress::plain(Key::Up));
+    assert_eq!(decode(b"\x1BOD"), KeyPress::plain(Key::Left));
+    assert_eq!(decode(b"\x1BOH"), KeyPress::plain(Key::Home));
+    assert_eq!(decode(b"\x1BOF"), KeyPress::plain(Key::End));
+    assert_eq!(decode(b"\x1BOP"), KeyPress::plain(Key::F(1)));
+    assert_eq!(decode(b"\x1BOQ"), KeyPress::plain(Key::F(2)));
+    assert_eq!(decode(b"\x1BOR"), KeyPress::plain(Key::F(3)));
+    assert_eq!(decode(b"\x1BOS"), KeyPress::plain(Key::F(4)));
+    assert_eq!(decode(b"\x1BO"), KeyPress::plain(Key::Unknown));
+}
+
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn modifiers() {
+    assert_eq!(decode(b"\x1B[1;2A"), with(Key::Up, false, false, true));
+    assert_eq!(decode(b"\x1B[1;3B"), with(Key::Down, false, true, false));
+    assert_eq!(decode(b"\x1B[1;5C"), with(Key::Right, true, false, false));
+    assert_eq!(decode(b"\x1B[1;8D"), with(Key::Left, true, true, true));
+    assert_eq!(decode(b"\x1B[3;5~"), with(Key::Delete, true, false, false));
+    assert_eq!(decode(b"\x1B[5;6~"), with(Key::PageUp, true, false, true));
+}
+
+#[cfg(target_os = "windows")]
+#[test]
+fn scan_codes() {
+    assert_eq!(decode(&[224, 72]), KeyPress::plain(Key::Up));
+    assert_eq!(decode(&[224, 80]), KeyPress::plain(Key::Down));
+    assert_eq!(decode(&[224, 75]), KeyPress::plain(Key::Left));
+    assert_eq!(decode(&[224, 77]), KeyPress::plain(Key::Right));
+    assert_eq!(decode(&[224, 83]), KeyPress::plain(Key::Delete));
+    assert_eq!(decode(&[224, 134]), KeyPress::plain(Key::F(12)));
+    assert_eq!(decode(&[224, 141]), with(Key::Up, true, false, false));
+}
+
+#[test]
+fn interrupt() {
+    assert!(decode(b"\x03").is_interrupt());
+    assert!(!decode(b"\x1B\x03").is_interrupt());
+    assert!(!decode(b"c").is_interrupt());
+}
@@ -0,0 +1,5 @@
+mod input;
+mod filter;
+mod netrc;
+mod cli;
+mod session;
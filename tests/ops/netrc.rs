@@ -0,0 +1,91 @@
+use doh::ops::netrc::parse;
+use doh::ops::Credentials;
+
+
+fn basic(username: &str, password: Option<&str>) -> Option<Credentials> {
+    Some(Credentials::Basic {
+        username: username.to_string(),
+        password: password.map(str::to_string),
+    })
+}
+
+
+#[test]
+fn machine() {
+    let netrc = "machine a.example login a password pa\nmachine b.example login b password pb";
+    assert_eq!(parse(netrc, "a.example"), basic("a", Some("pa")));
+    assert_eq!(parse(netrc, "b.example"), basic("b", Some("pb")));
+    assert_eq!(parse(netrc, "B.EXAMPLE"), basic("b", Some("pb")));
+    assert_eq!(parse(netrc, "c.example"), None);
+}
+
+#[test]
+fn single_line() {
+    assert_eq!(parse("machine a.example login a password pa machine b.example login b", "b.example"),
+               basic("b", None));
+}
+
+#[test]
+fn first_entry_wins() {
+    assert_eq!(parse("machine a.example login first\nmachine a.example login second", "a.example"),
+               basic("first", None));
+}
+
+#[test]
+fn default() {
+    let netrc = "machine a.example login a password pa\ndefault login anonymous password guest";
+    assert_eq!(parse(netrc, "a.example"), basic("a", Some("pa")));
+    assert_eq!(parse(netrc, "b.example"), basic("anonymous", Some("guest")));
+}
+
+#[test]
+fn default_only() {
+    assert_eq!(parse("default login anonymous", "anything.example"), basic("anonymous", None));
+}
+
+#[test]
+fn token() {
+    assert_eq!(parse("machine a.example token abc", "a.example"), Some(Credentials::Bearer("abc".to_string())));
+    assert_eq!(parse("machine a.example login a token abc", "a.example"), basic("a", None));
+}
+
+#[test]
+fn account_ignored() {
+    assert_eq!(parse("machine a.example account acct login a", "a.example"), basic("a", None));
+    assert_eq!(parse("machine a.example account login", "a.example"), None);
+}
+
+#[test]
+fn macdef() {
+    let netrc = "machine a.example login a\n\
+                 macdef init\n\
+                 machine b.example login evil\n\
+                 cd /\n\
+                 \n\
+                 machine b.example login b";
+    assert_eq!(parse(netrc, "a.example"), basic("a", None));
+    assert_eq!(parse(netrc, "b.example"), basic("b", None));
+}
+
+#[test]
+fn macdef_rest_of_line() {
+    assert_eq!(parse("macdef init machine a.example login evil\n\nmachine a.example login a", "a.example"),
+               basic("a", None));
+}
+
+#[test]
+fn quoting() {
+    assert_eq!(parse("machine a.example login \"John Doe\" password \"pass word\"", "a.example"),
+               basic("John Doe", Some("pass word")));
+    assert_eq!(parse(r#"machine a.example login a password "quote\" and \\ backslash""#, "a.example"),
+               basic("a", Some("quote\" and \\ backslash")));
+    assert_eq!(parse("machine a.example login a password \"\"", "a.example"), basic("a", Some("")));
+    assert_eq!(parse("machine a.example login a password \"unterminated", "a.example"),
+               basic("a", Some("unterminated")));
+}
+
+#[test]
+fn empty() {
+    assert_eq!(parse("", "a.example"), None);
+    assert_eq!(parse("  \n\t\n", "a.example"), None);
+}
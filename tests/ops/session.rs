@@ -0,0 +1,194 @@
+use doh::ops::{SessionConfig, Session};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::thread::{self, JoinHandle};
+use std::io::{Write, Read};
+use std::net::TcpListener;
+use filetime::{self, FileTime};
+use std::path::PathBuf;
+use reqwest::StatusCode;
+use reqwest::Url;
+use std::{env, fs};
+
+
+/// `Last-Modified` date of the served resource, and its value in seconds since the UNIX epoch.
+const LAST_MODIFIED: &str = "Fri, 14 Jul 2017 02:40:00 GMT";
+const LAST_MODIFIED_SECS: u64 = 1500000000;
+const CONTENT: &[u8] = b"hello world";
+
+
+/// Answer consecutive connections with the specified raw responses, one each,
+/// returning the URL to request and a handle yielding the heads of the requests received, lowercased.
+fn serve(responses: Vec<Vec<u8>>) -> (Url, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/file", listener.local_addr().unwrap()).parse().unwrap();
+    (url,
+     thread::spawn(move || {
+        responses.into_iter()
+            .map(|resp| {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut head = vec![];
+                let mut b = [0];
+                while !head.ends_with(b"\r\n\r\n") && stream.read(&mut b).unwrap() == 1 {
+                    head.push(b[0]);
+                }
+                stream.write_all(&resp).unwrap();
+                String::from_utf8(head).unwrap().to_lowercase()
+            })
+            .collect()
+    }))
+}
+
+fn response(status: &str, headers: &[String], body: &[u8]) -> Vec<u8> {
+    let mut resp = format!("HTTP/1.1 {}\r\nConnection: close\r\nLast-Modified: {}\r\n", status, LAST_MODIFIED);
+    for h in headers {
+        resp.push_str(h);
+        resp.push_str("\r\n");
+    }
+    resp.push_str("\r\n");
+    let mut resp = resp.into_bytes();
+    resp.extend(body);
+    resp
+}
+
+fn full() -> Vec<u8> {
+    response("200 OK", &[format!("Content-Length: {}", CONTENT.len())], CONTENT)
+}
+
+fn session() -> Session {
+    Session::new(&SessionConfig {
+            retries: 0,
+            keep_alive: false,
+            ..SessionConfig::default()
+        })
+        .unwrap()
+}
+
+/// Get a destination path, and its partial download path, with the latter holding `part`, if any.
+fn destination(name: &str, part: Option<&[u8]>) -> (PathBuf, PathBuf) {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos();
+    let dest = env::temp_dir().join(format!("doh-session-test-{}-{}", name, nanos));
+    let part_path = PathBuf::from(format!("{}.part", dest.display()));
+    if let Some(part) = part {
+        fs::File::create(&part_path).unwrap().write_all(part).unwrap();
+        let mtime = FileTime::from_seconds_since_1970(LAST_MODIFIED_SECS, 0);
+        filetime::set_file_times(&part_path, mtime, mtime).unwrap();
+    }
+    (dest, part_path)
+}
+
+fn read(p: &PathBuf) -> Vec<u8> {
+    let mut data = vec![];
+    fs::File::open(p).unwrap().read_to_end(&mut data).unwrap();
+    data
+}
+
+fn mtime_secs(p: &PathBuf) -> u64 {
+    FileTime::from_last_modification_time(&fs::metadata(p).unwrap()).seconds_relative_to_1970()
+}
+
+
+#[test]
+fn fresh_200() {
+    let (u, server) = serve(vec![full()]);
+    let (dest, part) = destination("fresh", None);
+
+    assert_eq!(session().download_resumable(u, &dest).unwrap(), StatusCode::Ok);
+    let requests = server.join().unwrap();
+    assert!(!requests[0].contains("\r\nrange:"), "{}", requests[0]);
+    assert_eq!(read(&dest), CONTENT);
+    assert_eq!(mtime_secs(&dest), LAST_MODIFIED_SECS);
+    assert!(!part.exists());
+    fs::remove_file(&dest).unwrap();
+}
+
+#[test]
+fn resume_206() {
+    let (u, server) = serve(vec![response("206 Partial Content",
+                                          &["Content-Range: bytes 6-10/11".to_string(), "Content-Length: 5".to_string()],
+                                          &CONTENT[6..])]);
+    let (dest, part) = destination("resume", Some(&CONTENT[..6]));
+
+    assert_eq!(session().download_resumable(u, &dest).unwrap(), StatusCode::PartialContent);
+    let requests = server.join().unwrap();
+    assert!(requests[0].contains("\r\nrange: bytes=6-\r\n"), "{}", requests[0]);
+    assert!(requests[0].contains(&format!("\r\nif-range: {}\r\n", LAST_MODIFIED.to_lowercase())), "{}", requests[0]);
+    assert_eq!(read(&dest), CONTENT);
+    assert!(!part.exists());
+    fs::remove_file(&dest).unwrap();
+}
+
+#[test]
+fn resume_206_wrong_offset() {
+    let (u, server) = serve(vec![response("206 Partial Content",
+                                          &["Content-Range: bytes 0-10/11".to_string(), "Content-Length: 11".to_string()],
+                                          CONTENT),
+                                 full()]);
+    let (dest, part) = destination("wrong-offset", Some(b"stale "));
+
+    assert_eq!(session().download_resumable(u, &dest).unwrap(), StatusCode::Ok);
+    let requests = server.join().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(!requests[1].contains("\r\nrange:"), "{}", requests[1]);
+    assert_eq!(read(&dest), CONTENT);
+    assert!(!part.exists());
+    fs::remove_file(&dest).unwrap();
+}
+
+#[test]
+fn resume_200_changed() {
+    let (u, server) = serve(vec![full()]);
+    let (dest, part) = destination("changed", Some(b"stale "));
+
+    assert_eq!(session().download_resumable(u, &dest).unwrap(), StatusCode::Ok);
+    let requests = server.join().unwrap();
+    assert!(requests[0].contains("\r\nrange: bytes=6-\r\n"), "{}", requests[0]);
+    assert_eq!(read(&dest), CONTENT);
+    assert!(!part.exists());
+    fs::remove_file(&dest).unwrap();
+}
+
+#[test]
+fn resume_416() {
+    let (u, server) = serve(vec![response("416 Range Not Satisfiable", &["Content-Length: 0".to_string()], b""), full()]);
+    let (dest, part) = destination("unsatisfiable", Some(b"longer than the resource"));
+
+    assert_eq!(session().download_resumable(u, &dest).unwrap(), StatusCode::Ok);
+    let requests = server.join().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[0].contains("\r\nrange: bytes=24-\r\n"), "{}", requests[0]);
+    assert!(!requests[1].contains("\r\nrange:"), "{}", requests[1]);
+    assert_eq!(read(&dest), CONTENT);
+    assert!(!part.exists());
+    fs::remove_file(&dest).unwrap();
+}
+
+#[test]
+fn truncated_then_resumed() {
+    let (u, server) = serve(vec![response("200 OK", &[format!("Content-Length: {}", CONTENT.len())], &CONTENT[..6]),
+                                 response("206 Partial Content",
+                                          &["Content-Range: bytes 6-10/11".to_string(), "Content-Length: 5".to_string()],
+                                          &CONTENT[6..])]);
+    let (dest, part) = destination("truncated", None);
+
+    assert!(session().download_resumable(u.clone(), &dest).is_err());
+    assert!(!dest.exists());
+    assert_eq!(read(&part), &CONTENT[..6]);
+    assert_eq!(mtime_secs(&part), LAST_MODIFIED_SECS);
+
+    assert_eq!(session().download_resumable(u, &dest).unwrap(), StatusCode::PartialContent);
+    server.join().unwrap();
+    assert_eq!(read(&dest), CONTENT);
+    assert!(!part.exists());
+    fs::remove_file(&dest).unwrap();
+}
+
+#[test]
+fn not_found() {
+    let (u, server) = serve(vec![response("404 Not Found", &["Content-Length: 0".to_string()], b"")]);
+    let (dest, part) = destination("not-found", None);
+
+    assert_eq!(session().download_resumable(u, &dest).unwrap(), StatusCode::NotFound);
+    server.join().unwrap();
+    assert!(!dest.exists());
+    assert!(!part.exists());
+}
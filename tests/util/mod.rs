@@ -1,4 +1,6 @@
-use doh::util::{RaiiGuard, TAB_SPACING, TAB_WIDTH};
+use doh::util::{RaiiGuard, MAX_RETRY_DELAY, TAB_SPACING, TAB_WIDTH, parse_retry_after, retry_backoff};
+use std::time::Duration;
+use time;
 
 
 #[test]
@@ -27,3 +29,57 @@ fn raii_guard() {
     assert_eq!(start, true);
     assert_eq!(unsafe { END }, true);
 }
+
+#[test]
+fn retry_backoff_doubles() {
+    let initial = Duration::from_millis(500);
+    assert_eq!(retry_backoff(initial, 0), Duration::from_millis(500));
+    assert_eq!(retry_backoff(initial, 1), Duration::from_secs(1));
+    assert_eq!(retry_backoff(initial, 2), Duration::from_secs(2));
+    assert_eq!(retry_backoff(initial, 7), Duration::from_secs(64));
+}
+
+#[test]
+fn retry_backoff_cap() {
+    let max = Duration::from_secs(MAX_RETRY_DELAY);
+    assert_eq!(retry_backoff(Duration::from_secs(1), 7), max);
+    assert_eq!(retry_backoff(Duration::from_secs(1), 16), max);
+    assert_eq!(retry_backoff(Duration::from_secs(1), usize::max_value()), max);
+    assert_eq!(retry_backoff(Duration::from_secs(0), 100), Duration::from_secs(0));
+}
+
+#[test]
+fn retry_backoff_long_initial() {
+    let initial = Duration::from_secs(MAX_RETRY_DELAY * 10);
+    assert_eq!(retry_backoff(initial, 0), initial);
+    assert_eq!(retry_backoff(initial, 5), initial);
+}
+
+#[test]
+fn retry_backoff_overflow() {
+    let initial = Duration::from_secs(u64::max_value() / 2);
+    assert_eq!(retry_backoff(initial, 0), initial);
+    assert_eq!(retry_backoff(initial, 4), initial);
+}
+
+#[test]
+fn retry_after_seconds() {
+    assert_eq!(parse_retry_after("0"), Some(0));
+    assert_eq!(parse_retry_after("120"), Some(120));
+    assert_eq!(parse_retry_after(" 30 "), Some(30));
+    assert_eq!(parse_retry_after("-1"), None);
+    assert_eq!(parse_retry_after("1.5"), None);
+    assert_eq!(parse_retry_after(""), None);
+}
+
+#[test]
+fn retry_after_date() {
+    assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(0));
+
+    let future = time::now_utc() + time::Duration::hours(1);
+    let header = time::strftime("%a, %d %b %Y %H:%M:%S GMT", &future).unwrap();
+    let delay = parse_retry_after(&header).unwrap();
+    assert!(delay > 3590 && delay <= 3600, "{}", delay);
+
+    assert_eq!(parse_retry_after("21 Oct 2015"), None);
+}